@@ -10,8 +10,12 @@ OPTIONS:
         --version                       Show version number
         --font-file PATH                Set font file-name
         --face-index INDEX              Set face index [default: 0]
+        --font-size NUMBER              Set font scale [default: units per EM]
+        --font-ppem NUMBER              Set font pixels per EM
         --font-ptem NUMBER              Set font point-size
         --variations LIST               Set comma-separated list of font variations
+        --font-bold NUMBER              Set synthetic bold in fractions of EM [default: 0]
+        --font-slant NUMBER             Set synthetic slant [default: 0]
         --text TEXT                     Set input text
         --text-file PATH                Set input text file
     -u, --unicodes LIST                 Set comma-separated list of input Unicode codepoints
@@ -20,10 +24,19 @@ OPTIONS:
                                         [possible values: ltr, rtl, ttb, btt]
         --language LANG                 Set text language [default: LC_CTYPE]
         --script TAG                    Set text script as ISO-15924 tag
+        --bot                           Treat text as beginning-of-paragraph
+        --eot                           Treat text as end-of-paragraph
+        --preserve-default-ignorables   Preserve Default-Ignorable characters
+        --remove-default-ignorables     Remove Default-Ignorable characters
+        --do-not-insert-dotted-circle   Do not insert dotted circle glyphs
+        --unsafe-to-concat              Produce unsafe-to-concat glyph flag
+        --safe-to-insert-tatweel        Produce safe-to-insert-tatweel glyph flag
         --utf8-clusters                 Use UTF-8 byte indices, not char indices
         --cluster-level N               Cluster merging level [default: 0]
-                                        [possible values: 0, 1, 2]
+                                        [possible values: 0, 1, 2, 3]
         --features LIST                 Set comma-separated list of font features
+        --output-format FORMAT          Set output format [default: text]
+                                        [possible values: text, json]
         --no-glyph-names                Output glyph indices instead of names
         --no-positions                  Do not output glyph positions
         --no-advances                   Do not output glyph advances
@@ -42,17 +55,29 @@ struct Args {
     version: bool,
     font_file: Option<PathBuf>,
     face_index: u32,
+    font_size: Option<i32>,
+    font_ppem: Option<u16>,
     font_ptem: Option<f32>,
     variations: Vec<rustybuzz::Variation>,
+    font_bold: f32,
+    font_slant: f32,
     text: Option<String>,
     text_file: Option<PathBuf>,
     unicodes: Option<String>,
     direction: Option<rustybuzz::Direction>,
     language: rustybuzz::Language,
     script: Option<rustybuzz::Script>,
+    bot: bool,
+    eot: bool,
+    preserve_default_ignorables: bool,
+    remove_default_ignorables: bool,
+    do_not_insert_dotted_circle: bool,
+    unsafe_to_concat: bool,
+    safe_to_insert_tatweel: bool,
     utf8_clusters: bool,
     cluster_level: rustybuzz::BufferClusterLevel,
     features: Vec<rustybuzz::Feature>,
+    output_format: rustybuzz::SerializeFormat,
     no_glyph_names: bool,
     no_positions: bool,
     no_advances: bool,
@@ -70,17 +95,29 @@ fn parse_args() -> Result<Args, pico_args::Error> {
         version: args.contains("--version"),
         font_file: args.opt_value_from_str("--font-file")?,
         face_index: args.opt_value_from_str("--face-index")?.unwrap_or(0),
+        font_size: args.opt_value_from_str("--font-size")?,
+        font_ppem: args.opt_value_from_str("--font-ppem")?,
         font_ptem: args.opt_value_from_str("--font-ptem")?,
         variations: args.opt_value_from_fn("--variations", parse_variations)?.unwrap_or_default(),
+        font_bold: args.opt_value_from_str("--font-bold")?.unwrap_or(0.0),
+        font_slant: args.opt_value_from_str("--font-slant")?.unwrap_or(0.0),
         text: args.opt_value_from_str("--text")?,
         text_file: args.opt_value_from_str("--text-file")?,
         unicodes: args.opt_value_from_fn(["-u", "--unicodes"], parse_unicodes)?,
         direction: args.opt_value_from_str("--direction")?,
         language: args.opt_value_from_str("--language")?.unwrap_or(system_language()),
         script: args.opt_value_from_str("--script")?,
+        bot: args.contains("--bot"),
+        eot: args.contains("--eot"),
+        preserve_default_ignorables: args.contains("--preserve-default-ignorables"),
+        remove_default_ignorables: args.contains("--remove-default-ignorables"),
+        do_not_insert_dotted_circle: args.contains("--do-not-insert-dotted-circle"),
+        unsafe_to_concat: args.contains("--unsafe-to-concat"),
+        safe_to_insert_tatweel: args.contains("--safe-to-insert-tatweel"),
         utf8_clusters: args.contains("--utf8-clusters"),
         cluster_level: args.opt_value_from_fn("--cluster-level", parse_cluster)?.unwrap_or_default(),
         features: args.opt_value_from_fn("--features", parse_features)?.unwrap_or_default(),
+        output_format: args.opt_value_from_fn("--output-format", parse_output_format)?.unwrap_or_default(),
         no_glyph_names: args.contains("--no-glyph-names"),
         no_positions: args.contains("--no-positions"),
         no_advances: args.contains("--no-advances"),
@@ -132,12 +169,20 @@ fn main() {
     let font_data = std::fs::read(font_path).unwrap();
     let mut face = rustybuzz::Face::from_slice(&font_data, args.face_index).unwrap();
 
+    if let Some(size) = args.font_size {
+        face.set_scale(size, size);
+    }
+
+    face.set_pixels_per_em(args.font_ppem.map(|ppem| (ppem, ppem)));
     face.set_points_per_em(args.font_ptem);
 
     if !args.variations.is_empty() {
         face.set_variations(&args.variations);
     }
 
+    face.set_synthetic_bold(args.font_bold, args.font_bold, false);
+    face.set_synthetic_slant(args.font_slant);
+
     let text = if let Some(path) = args.text_file {
         std::fs::read_to_string(path).unwrap()
     } else if args.free.len() == 2 && font_set_as_free_arg {
@@ -153,8 +198,14 @@ fn main() {
         std::process::exit(1);
     };
 
+    let cluster_unit = if args.utf8_clusters {
+        rustybuzz::ClusterUnit::Utf8
+    } else {
+        rustybuzz::ClusterUnit::Codepoint
+    };
+
     let mut buffer = rustybuzz::UnicodeBuffer::new();
-    buffer.push_str(&text);
+    buffer.push_utf8(text.as_bytes(), 0..text.len(), cluster_unit);
 
     if let Some(d) = args.direction {
         buffer.set_direction(d);
@@ -166,11 +217,17 @@ fn main() {
         buffer.set_script(script);
     }
 
-    buffer.set_cluster_level(args.cluster_level);
+    let mut buffer_flags = rustybuzz::BufferFlags::default();
+    buffer_flags.set(rustybuzz::BufferFlags::BEGINNING_OF_TEXT, args.bot);
+    buffer_flags.set(rustybuzz::BufferFlags::END_OF_TEXT, args.eot);
+    buffer_flags.set(rustybuzz::BufferFlags::PRESERVE_DEFAULT_IGNORABLES, args.preserve_default_ignorables);
+    buffer_flags.set(rustybuzz::BufferFlags::REMOVE_DEFAULT_IGNORABLES, args.remove_default_ignorables);
+    buffer_flags.set(rustybuzz::BufferFlags::DO_NOT_INSERT_DOTTED_CIRCLE, args.do_not_insert_dotted_circle);
+    buffer_flags.set(rustybuzz::BufferFlags::PRODUCE_UNSAFE_TO_CONCAT, args.unsafe_to_concat);
+    buffer_flags.set(rustybuzz::BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL, args.safe_to_insert_tatweel);
+    buffer.set_flags(buffer_flags);
 
-    if !args.utf8_clusters {
-        buffer.reset_clusters();
-    }
+    buffer.set_cluster_level(args.cluster_level);
 
     let glyph_buffer = rustybuzz::shape(&face, &args.features, buffer);
 
@@ -199,7 +256,7 @@ fn main() {
         format_flags |= rustybuzz::SerializeFlags::GLYPH_FLAGS;
     }
 
-    println!("{}", glyph_buffer.serialize(&face,  format_flags));
+    println!("{}", glyph_buffer.serialize_with_format(&face, args.output_format, format_flags));
 }
 
 fn parse_unicodes(s: &str) -> Result<String, String> {
@@ -241,10 +298,19 @@ fn parse_cluster(s: &str) -> Result<rustybuzz::BufferClusterLevel, String> {
         "0" => Ok(rustybuzz::BufferClusterLevel::MonotoneGraphemes),
         "1" => Ok(rustybuzz::BufferClusterLevel::MonotoneCharacters),
         "2" => Ok(rustybuzz::BufferClusterLevel::Characters),
+        "3" => Ok(rustybuzz::BufferClusterLevel::Graphemes),
         _ => Err(format!("invalid cluster level"))
     }
 }
 
+fn parse_output_format(s: &str) -> Result<rustybuzz::SerializeFormat, String> {
+    match s {
+        "text" => Ok(rustybuzz::SerializeFormat::Text),
+        "json" => Ok(rustybuzz::SerializeFormat::Json),
+        _ => Err(format!("invalid output format"))
+    }
+}
+
 fn system_language() -> rustybuzz::Language {
     unsafe {
         libc::setlocale(libc::LC_ALL, b"\0" as *const _ as *const i8);
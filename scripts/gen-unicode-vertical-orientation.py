@@ -0,0 +1,56 @@
+#!/usr/bin/env python
+
+import urllib.request
+import os
+
+# Unlike the normalization tables (13.0.0), this matches the version of the
+# `unicode-general-category` and `unicode-script` crates, since sideways runs
+# are split by general category and shaped in the direction of their script.
+URL = 'https://www.unicode.org/Public/15.0.0/ucd/VerticalOrientation.txt'
+FILE_NAME = 'VerticalOrientation.txt'
+
+if not os.path.exists(FILE_NAME):
+    urllib.request.urlretrieve(URL, FILE_NAME)
+
+NAMES = {
+    'U': 'Upright',
+    'Tu': 'TransformedUpright',
+    'Tr': 'TransformedRotated',
+}
+
+ranges = []
+with open(FILE_NAME) as f:
+    for line in f:
+        line = line.split('#')[0].strip()
+        if not line:
+            continue
+
+        range, value = line.split(';')
+        range = range.strip()
+        value = value.strip()
+
+        # Rotated is the default.
+        if value == 'R':
+            continue
+
+        if '..' in range:
+            start, end = range.split('..')
+        else:
+            start, end = range, range
+
+        ranges.append([start, end, value])
+
+# Merge ranges.
+idx = 0
+while idx < len(ranges)-1:
+    if int(ranges[idx][1], 16) + 1 == int(ranges[idx+1][0], 16) and ranges[idx][2] == ranges[idx+1][2]:
+        ranges[idx][1] = ranges[idx+1][1]
+        del ranges[idx+1]
+    else:
+        idx += 1;
+
+for start, end, value in ranges:
+    if start == end:
+        print('0x{} => {},'.format(start, NAMES[value]))
+    else:
+        print('0x{}..=0x{} => {},'.format(start, end, NAMES[value]))
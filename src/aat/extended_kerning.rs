@@ -73,6 +73,7 @@ pub(crate) fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Optio
             }
             kerx::Format::Format1(ref sub) => {
                 let mut driver = Driver1 {
+                    face,
                     stack: [0; 8],
                     depth: 0,
                 };
@@ -88,6 +89,7 @@ pub(crate) fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Optio
             }
             kerx::Format::Format4(ref sub) => {
                 let mut driver = Driver4 {
+                    face,
                     mark_set: false,
                     mark: 0,
                     ankr_table: face.tables().ankr.clone(),
@@ -132,7 +134,9 @@ fn apply_simple_kerning(
         }
 
         let mut iter = SkippyIter::new(&ctx, i, 1, false);
-        if !iter.next() {
+        let mut unsafe_to = 0;
+        if !iter.next(Some(&mut unsafe_to)) {
+            ctx.buffer.unsafe_to_concat(i, unsafe_to);
             i += 1;
             continue;
         }
@@ -145,6 +149,7 @@ fn apply_simple_kerning(
 
         let pos = &mut ctx.buffer.pos;
         if kern != 0 {
+            let kern = if horizontal { ctx.face.em_scale_x(kern) } else { ctx.face.em_scale_y(kern) };
             if horizontal {
                 if subtable.has_cross_stream {
                     pos[j].y_offset = kern;
@@ -170,6 +175,8 @@ fn apply_simple_kerning(
             }
 
             ctx.buffer.unsafe_to_break(i, j + 1)
+        } else {
+            ctx.buffer.unsafe_to_concat(i, j + 1);
         }
 
         i = j;
@@ -264,12 +271,13 @@ trait StateTableDriver<Table, E: FromData> {
 }
 
 
-struct Driver1 {
+struct Driver1<'a> {
+    face: &'a Face<'a>,
     stack: [usize; 8],
     depth: usize,
 }
 
-impl StateTableDriver<kerx::Subtable1<'_>, kerx::EntryData> for Driver1 {
+impl StateTableDriver<kerx::Subtable1<'_>, kerx::EntryData> for Driver1<'_> {
     fn is_actionable(&self, entry: apple_layout::GenericStateEntry<kerx::EntryData>) -> bool {
         entry.is_actionable()
     }
@@ -335,12 +343,12 @@ impl StateTableDriver<kerx::Subtable1<'_>, kerx::EntryData> for Driver1 {
                             pos.set_attach_chain(0);
                             pos.y_offset = 0;
                         } else if pos.attach_type() != 0 {
-                            pos.y_offset += v;
+                            pos.y_offset += self.face.em_scale_y(v);
                             has_gpos_attachment = true;
                         }
                     } else if glyph_mask & plan.kern_mask != 0 {
-                        pos.x_advance += v;
-                        pos.x_offset += v;
+                        pos.x_advance += self.face.em_scale_x(v);
+                        pos.x_offset += self.face.em_scale_x(v);
                     }
                 } else {
                     if has_cross_stream {
@@ -350,13 +358,13 @@ impl StateTableDriver<kerx::Subtable1<'_>, kerx::EntryData> for Driver1 {
                             pos.set_attach_chain(0);
                             pos.x_offset = 0;
                         } else if pos.attach_type() != 0 {
-                            pos.x_offset += v;
+                            pos.x_offset += self.face.em_scale_x(v);
                             has_gpos_attachment = true;
                         }
                     } else if glyph_mask & plan.kern_mask != 0 {
                         if pos.y_offset == 0 {
-                            pos.y_advance += v;
-                            pos.y_offset += v;
+                            pos.y_advance += self.face.em_scale_y(v);
+                            pos.y_offset += self.face.em_scale_y(v);
                         }
                     }
                 }
@@ -373,6 +381,7 @@ impl StateTableDriver<kerx::Subtable1<'_>, kerx::EntryData> for Driver1 {
 
 
 struct Driver4<'a> {
+    face: &'a Face<'a>,
     mark_set: bool,
     mark: usize,
     ankr_table: Option<ankr::Table<'a>>,
@@ -410,8 +419,10 @@ impl StateTableDriver<kerx::Subtable4<'_>, kerx::EntryData> for Driver4<'_> {
                     .unwrap_or_default();
 
                 let pos = buffer.cur_pos_mut();
-                pos.x_offset = i32::from(mark_anchor.x - curr_anchor.x);
-                pos.y_offset = i32::from(mark_anchor.y - curr_anchor.y);
+                pos.x_offset = self.face.em_scale_x(i32::from(mark_anchor.x))
+                    - self.face.em_scale_x(i32::from(curr_anchor.x));
+                pos.y_offset = self.face.em_scale_y(i32::from(mark_anchor.y))
+                    - self.face.em_scale_y(i32::from(curr_anchor.y));
             }
 
             buffer.cur_pos_mut().set_attach_type(attach_type::MARK);
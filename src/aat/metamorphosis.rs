@@ -40,9 +40,19 @@ pub fn compile_flags(face: &Face, builder: &MapBuilder) -> Option<Map> {
 }
 
 pub fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Option<()> {
-    for (chain_idx, chain) in face.tables().morx.as_ref()?.chains.into_iter().enumerate() {
+    let chains = face.tables().morx.as_ref()?.chains;
+    if !buffer.message(face, format_args!("start table morx")) {
+        return Some(());
+    }
+
+    // Subtables are numbered across all chains.
+    let mut subtable_index = 0;
+    for (chain_idx, chain) in chains.into_iter().enumerate() {
         let flags = plan.aat_map.chain_flags[chain_idx];
         for subtable in chain.subtables {
+            let index = subtable_index;
+            subtable_index += 1;
+
             if subtable.feature_flags & flags == 0 {
                 continue;
             }
@@ -86,6 +96,10 @@ pub fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Option<()> {
                 subtable.coverage.is_backwards() != buffer.direction.is_backward()
             };
 
+            if !buffer.message(face, format_args!("start chainsubtable {}", index)) {
+                continue;
+            }
+
             if reverse {
                 buffer.reverse();
             }
@@ -95,9 +109,12 @@ pub fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Option<()> {
             if reverse {
                 buffer.reverse();
             }
+
+            buffer.message(face, format_args!("end chainsubtable {}", index));
         }
     }
 
+    buffer.message(face, format_args!("end table morx"));
     Some(())
 }
 
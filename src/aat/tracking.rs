@@ -18,8 +18,8 @@ pub fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Option<()> {
 
     if buffer.direction.is_horizontal() {
         let tracking = trak.hor_tracking(ptem)?;
-        let advance_to_add = tracking;
-        let offset_to_add = tracking / 2;
+        let advance_to_add = face.em_scale_x(tracking);
+        let offset_to_add = face.em_scale_x(tracking / 2);
         foreach_grapheme!(buffer, start, end, {
             if buffer.info[start].mask & trak_mask != 0 {
                 buffer.pos[start].x_advance += advance_to_add;
@@ -28,8 +28,8 @@ pub fn apply(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) -> Option<()> {
         });
     } else {
         let tracking = trak.ver_tracking(ptem)?;
-        let advance_to_add = tracking;
-        let offset_to_add = tracking / 2;
+        let advance_to_add = face.em_scale_y(tracking);
+        let offset_to_add = face.em_scale_y(tracking / 2);
         foreach_grapheme!(buffer, start, end, {
             if buffer.info[start].mask & trak_mask != 0 {
                 buffer.pos[start].y_advance += advance_to_add;
@@ -1,12 +1,15 @@
 use alloc::{string::String, vec::Vec};
 use core::convert::TryFrom;
+use core::fmt;
 
 use ttf_parser::GlyphId;
 
 use crate::{script, Direction, Face, Language, Mask, Script};
+use crate::text_parser::TextParser;
 use crate::unicode::{CharExt, GeneralCategory, GeneralCategoryExt, Space};
 
 const CONTEXT_LENGTH: usize = 5;
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
 
 
 pub mod glyph_flag {
@@ -27,8 +30,30 @@ pub mod glyph_flag {
     /// breaking point only.
     pub const UNSAFE_TO_BREAK: u32 = 0x00000001;
 
+    /// Indicates that if input text is changed on one
+    /// side of the beginning of the cluster this glyph
+    /// is part of, then the shaping results for the
+    /// other side might change.  Note that the absence
+    /// of this flag will NOT by itself mean that it
+    /// IS safe to concat text.  Only two pieces of text
+    /// both of which clear of this flag can be concatenated
+    /// safely.  This can be used to optimize paragraph
+    /// layout, by avoiding re-shaping of each line
+    /// after line-breaking.  `UNSAFE_TO_BREAK` implies
+    /// this flag.  Only produced when the
+    /// `PRODUCE_UNSAFE_TO_CONCAT` buffer flag is set.
+    pub const UNSAFE_TO_CONCAT: u32 = 0x00000002;
+
+    /// In scripts that use elongation (Arabic,
+    /// Mongolian, Syriac, etc.), this flag signifies
+    /// that it is safe to insert a U+0640 TATWEEL
+    /// character before this cluster for elongation.
+    /// Only produced when the `PRODUCE_SAFE_TO_INSERT_TATWEEL`
+    /// buffer flag is set.
+    pub const SAFE_TO_INSERT_TATWEEL: u32 = 0x00000004;
+
     /// All the currently defined flags.
-    pub const DEFINED: u32 = 0x00000001; // OR of all defined flags
+    pub const DEFINED: u32 = 0x00000007; // OR of all defined flags
 }
 
 
@@ -84,6 +109,22 @@ impl GlyphPosition {
         let v: &mut [u8; 4] = bytemuck::cast_mut(&mut self.var);
         v[2] = n;
     }
+
+    /// Indicates that the glyph is set sideways in vertical text,
+    /// i.e. its outline has to be rotated 90 degrees clockwise around the glyph origin.
+    ///
+    /// Only computed when `BufferFlags::ROTATE_SIDEWAYS` is set.
+    #[inline]
+    pub fn sideways(&self) -> bool {
+        let v: &[u8; 4] = bytemuck::cast_ref(&self.var);
+        v[3] != 0
+    }
+
+    #[inline]
+    pub(crate) fn set_sideways(&mut self, sideways: bool) {
+        let v: &mut [u8; 4] = bytemuck::cast_mut(&mut self.var);
+        v[3] = u8::from(sideways);
+    }
 }
 
 
@@ -127,6 +168,25 @@ impl GlyphInfo {
         self.mask & glyph_flag::UNSAFE_TO_BREAK != 0
     }
 
+    /// Indicates that if input text is changed on one side of the beginning of the cluster
+    /// this glyph is part of, then the shaping results for the other side might change.
+    ///
+    /// Only two pieces of text both of which clear of this flag can be concatenated safely.
+    /// `unsafe_to_break` implies this flag.
+    ///
+    /// Only computed when `BufferFlags::PRODUCE_UNSAFE_TO_CONCAT` is set.
+    pub fn unsafe_to_concat(&self) -> bool {
+        self.mask & glyph_flag::UNSAFE_TO_CONCAT != 0
+    }
+
+    /// Indicates that it is safe to insert a U+0640 TATWEEL character
+    /// before this cluster for elongation.
+    ///
+    /// Only computed when `BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL` is set.
+    pub fn safe_to_insert_tatweel(&self) -> bool {
+        self.mask & glyph_flag::SAFE_TO_INSERT_TATWEEL != 0
+    }
+
     #[inline]
     pub(crate) fn as_char(&self) -> char {
         char::try_from(self.glyph_id).unwrap()
@@ -329,6 +389,19 @@ impl GlyphInfo {
     // The numbers are also used in GPOS to do mark-to-mark positioning only
     // to marks that belong to the same component of the same ligature.
 
+    /// Returns the number of components of a ligature glyph.
+    ///
+    /// Returns 1 for glyphs that are not ligatures. Only OpenType ligatures are tracked,
+    /// and the count is limited to 15 components.
+    ///
+    /// With the [`BufferClusterLevel::Characters`] level, each component usually
+    /// corresponds to one input character, which can be used to position a cursor
+    /// inside of a ligature.
+    #[inline]
+    pub fn ligature_component_count(&self) -> u8 {
+        self.lig_num_comps()
+    }
+
     #[inline]
     pub(crate) fn lig_props(&self) -> u8 {
         let v: &[u8; 4] = bytemuck::cast_ref(&self.var1);
@@ -476,12 +549,60 @@ impl GlyphInfo {
 
 
 /// A cluster level.
-#[allow(missing_docs)]
+///
+/// Controls how input characters are grouped into clusters during shaping.
+/// Each glyph gets the cluster value of the first character of its cluster.
+/// When several glyphs or characters are merged into one cluster,
+/// all of them get the smallest cluster value.
+///
+/// Clusters are merged in two cases:
+///
+/// - During clusters formation, before any substitution, when the level
+///   groups graphemes. Each grapheme, i.e. a base character with all
+///   the following marks, becomes one cluster.
+/// - During shaping, when glyphs interact, like when a ligature is formed,
+///   a character is decomposed or marks are reordered. Monotone levels merge
+///   the clusters involved, so that cluster values never decrease in the
+///   output. Non-monotone levels keep them as is and only mark the glyphs
+///   as unsafe to break instead.
+///
+/// With the non-monotone [`Characters`](BufferClusterLevel::Characters) level,
+/// every input character keeps its own cluster, unless it becomes a part
+/// of a ligature or is removed. Use [`GlyphInfo::ligature_component_count`]
+/// to find how many characters a ligature glyph covers.
+///
+/// [Read more on clusters](https://harfbuzz.github.io/clusters.html).
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum BufferClusterLevel {
+    /// Graphemes are merged into one cluster and clusters are merged
+    /// to be monotone.
+    ///
+    /// This is the default level and matches the legacy behavior.
     MonotoneGraphemes,
+    /// Each character starts in its own cluster, but clusters are merged
+    /// to be monotone.
     MonotoneCharacters,
+    /// Each character starts in its own cluster and clusters are not merged.
+    ///
+    /// Output clusters may be in any order.
     Characters,
+    /// Graphemes are merged into one cluster, but clusters are not merged
+    /// during shaping.
+    ///
+    /// Output clusters may be in any order.
+    Graphemes,
+}
+
+impl BufferClusterLevel {
+    #[inline]
+    pub(crate) fn is_monotone(self) -> bool {
+        matches!(self, BufferClusterLevel::MonotoneGraphemes | BufferClusterLevel::MonotoneCharacters)
+    }
+
+    #[inline]
+    pub(crate) fn is_graphemes(self) -> bool {
+        matches!(self, BufferClusterLevel::MonotoneGraphemes | BufferClusterLevel::Graphemes)
+    }
 }
 
 impl Default for BufferClusterLevel {
@@ -492,11 +613,92 @@ impl Default for BufferClusterLevel {
 }
 
 
+/// A unit in which cluster values are counted when text is added to a buffer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ClusterUnit {
+    /// UTF-8 code units, i.e. bytes.
+    Utf8,
+    /// UTF-16 code units.
+    Utf16,
+    /// Unicode codepoints.
+    Codepoint,
+}
+
+impl ClusterUnit {
+    #[inline]
+    fn char_len(self, c: char) -> usize {
+        match self {
+            ClusterUnit::Utf8 => c.len_utf8(),
+            ClusterUnit::Utf16 => c.len_utf16(),
+            ClusterUnit::Codepoint => 1,
+        }
+    }
+}
+
+// Decoders yield chars along with their offsets in code units.
+// Invalid sequences are replaced with `replacement`.
+
+fn decode_utf8(text: &[u8], replacement: char) -> impl Iterator<Item = (usize, char)> + '_ {
+    fn first_char(s: &str) -> (char, usize) {
+        let c = s.chars().next().unwrap();
+        (c, c.len_utf8())
+    }
+
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        let rest = &text[offset..];
+        if rest.is_empty() {
+            return None;
+        }
+
+        // A char is at most 4 bytes long.
+        let chunk = &rest[..rest.len().min(4)];
+        let (c, len) = match core::str::from_utf8(chunk) {
+            Ok(s) => first_char(s),
+            Err(e) if e.valid_up_to() != 0 => {
+                first_char(core::str::from_utf8(&chunk[..e.valid_up_to()]).unwrap())
+            }
+            // A truncated sequence at the end of text.
+            Err(e) => (replacement, e.error_len().unwrap_or(chunk.len())),
+        };
+
+        let start = offset;
+        offset += len;
+        Some((start, c))
+    })
+}
+
+fn decode_utf16(text: &[u16], replacement: char) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut offset = 0;
+    core::char::decode_utf16(text.iter().copied()).map(move |c| {
+        let start = offset;
+        let c = match c {
+            Ok(c) => {
+                offset += c.len_utf16();
+                c
+            }
+            Err(_) => {
+                offset += 1;
+                replacement
+            }
+        };
+
+        (start, c)
+    })
+}
+
+fn decode_codepoints(text: &[u32], replacement: char) -> impl Iterator<Item = (usize, char)> + '_ {
+    text.iter().enumerate().map(move |(i, c)| (i, char::from_u32(*c).unwrap_or(replacement)))
+}
+
+
 pub struct Buffer {
     // Information about how the text in the buffer should be treated.
     pub flags: BufferFlags,
     pub cluster_level: BufferClusterLevel,
+    pub replacement: char,
     pub invisible: Option<GlyphId>,
+    pub not_found: GlyphId,
     pub scratch_flags: BufferScratchFlags,
     // Maximum allowed len.
     pub max_len: usize,
@@ -530,8 +732,14 @@ pub struct Buffer {
     // Index 0 is for "pre-context", 1 for "post-context".
     pub context: [[char; CONTEXT_LENGTH]; 2],
     pub context_len: [usize; 2],
+
+    #[cfg(feature = "trace")]
+    message_func: Option<alloc::boxed::Box<MessageFunc>>,
 }
 
+#[cfg(feature = "trace")]
+type MessageFunc = dyn FnMut(&BufferView, &str) -> bool + Send + Sync;
+
 impl Buffer {
     pub const MAX_LEN_FACTOR: usize = 32;
     pub const MAX_LEN_MIN: usize = 8192;
@@ -548,7 +756,9 @@ impl Buffer {
         Buffer {
             flags: BufferFlags::empty(),
             cluster_level: BufferClusterLevel::default(),
+            replacement: REPLACEMENT_CHARACTER,
             invisible: None,
+            not_found: GlyphId(0),
             scratch_flags: BufferScratchFlags::default(),
             max_len: Self::MAX_LEN_DEFAULT,
             max_ops: Self::MAX_OPS_DEFAULT,
@@ -567,9 +777,49 @@ impl Buffer {
             serial: 0,
             context: [['\0', '\0', '\0', '\0', '\0'], ['\0', '\0', '\0', '\0', '\0']],
             context_len: [0, 0],
+            #[cfg(feature = "trace")]
+            message_func: None,
         }
     }
 
+    /// Sends a trace message to the message function, if any.
+    ///
+    /// Returns `false` when the message function asks to skip the step.
+    #[cfg(feature = "trace")]
+    pub fn message(&mut self, face: &Face, args: fmt::Arguments) -> bool {
+        let mut func = match self.message_func.take() {
+            Some(func) => func,
+            None => return true,
+        };
+
+        let message = alloc::fmt::format(args);
+        let result = func(&BufferView { buffer: self, face }, &message);
+        self.message_func = Some(func);
+        result
+    }
+
+    /// Sends a trace message to the message function, if any.
+    ///
+    /// Does nothing without the `trace` feature.
+    #[cfg(not(feature = "trace"))]
+    #[inline(always)]
+    pub fn message(&mut self, _: &Face, _: fmt::Arguments) -> bool {
+        true
+    }
+
+    /// Moves the message function to another buffer, like a run created by `sub_buffer`.
+    #[cfg(feature = "trace")]
+    pub(crate) fn move_message_func(&mut self, to: &mut Buffer) {
+        to.message_func = self.message_func.take();
+    }
+
+    /// Moves the message function to another buffer, like a run created by `sub_buffer`.
+    ///
+    /// Does nothing without the `trace` feature.
+    #[cfg(not(feature = "trace"))]
+    #[inline(always)]
+    pub(crate) fn move_message_func(&mut self, _: &mut Buffer) {}
+
     #[inline]
     pub fn info_slice(&self) -> &[GlyphInfo] {
         &self.info[..self.len]
@@ -979,15 +1229,24 @@ impl Buffer {
             return;
         }
 
+        if !self.cluster_level.is_monotone() {
+            self.unsafe_to_break(start, end);
+            return;
+        }
+
         self.merge_clusters_impl(start, end)
     }
 
-    fn merge_clusters_impl(&mut self, mut start: usize, mut end: usize) {
-        if self.cluster_level == BufferClusterLevel::Characters {
-            self.unsafe_to_break(start, end);
+    /// Merges a grapheme into one cluster, regardless of the cluster level monotonicity.
+    pub fn merge_grapheme_clusters(&mut self, start: usize, end: usize) {
+        if end - start < 2 {
             return;
         }
 
+        self.merge_clusters_impl(start, end)
+    }
+
+    fn merge_clusters_impl(&mut self, mut start: usize, mut end: usize) {
         let mut cluster = self.info[start].cluster;
 
         for i in start+1..end {
@@ -1019,7 +1278,7 @@ impl Buffer {
     }
 
     pub fn merge_out_clusters(&mut self, mut start: usize, mut end: usize) {
-        if self.cluster_level == BufferClusterLevel::Characters {
+        if !self.cluster_level.is_monotone() {
             return;
         }
 
@@ -1143,41 +1402,94 @@ impl Buffer {
     }
 
     pub fn unsafe_to_break(&mut self, start: usize, end: usize) {
-        if end - start < 2 {
+        self.set_glyph_flags(
+            glyph_flag::UNSAFE_TO_BREAK | glyph_flag::UNSAFE_TO_CONCAT,
+            start,
+            end,
+            true,
+            false,
+        );
+    }
+
+    pub fn unsafe_to_concat(&mut self, start: usize, end: usize) {
+        if !self.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT) {
             return;
         }
 
-        self.unsafe_to_break_impl(start, end);
+        self.set_glyph_flags(glyph_flag::UNSAFE_TO_CONCAT, start, end, false, false);
     }
 
-    fn unsafe_to_break_impl(&mut self, start: usize, end: usize) {
-        let mut cluster = core::u32::MAX;
-        cluster = Self::_unsafe_to_break_find_min_cluster(&self.info, start, end, cluster);
-        let unsafe_to_break = Self::_unsafe_to_break_set_mask(&mut self.info, start, end, cluster);
-        if unsafe_to_break {
-            self.scratch_flags |= BufferScratchFlags::HAS_UNSAFE_TO_BREAK;
+    pub fn safe_to_insert_tatweel(&mut self, start: usize, end: usize) {
+        if !self.flags.contains(BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL) {
+            self.unsafe_to_break(start, end);
+            return;
         }
+
+        self.set_glyph_flags(glyph_flag::SAFE_TO_INSERT_TATWEEL, start, end, true, false);
     }
 
     pub fn unsafe_to_break_from_outbuffer(&mut self, start: usize, end: usize) {
-        if !self.have_output {
-            self.unsafe_to_break_impl(start, end);
+        self.set_glyph_flags(
+            glyph_flag::UNSAFE_TO_BREAK | glyph_flag::UNSAFE_TO_CONCAT,
+            start,
+            end,
+            true,
+            true,
+        );
+    }
+
+    pub fn unsafe_to_concat_from_outbuffer(&mut self, start: usize, end: usize) {
+        if !self.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT) {
             return;
         }
 
-        assert!(start <= self.out_len);
-        assert!(self.idx <= end);
+        self.set_glyph_flags(glyph_flag::UNSAFE_TO_CONCAT, start, end, false, true);
+    }
 
-        let mut cluster = core::u32::MAX;
-        cluster = Self::_unsafe_to_break_find_min_cluster(self.out_info(), start, self.out_len, cluster);
-        cluster = Self::_unsafe_to_break_find_min_cluster(&self.info, self.idx, end, cluster);
-        let idx = self.idx;
-        let out_len = self.out_len;
-        let unsafe_to_break1 = Self::_unsafe_to_break_set_mask(self.out_info_mut(), start, out_len, cluster);
-        let unsafe_to_break2 = Self::_unsafe_to_break_set_mask(&mut self.info, idx, end, cluster);
+    // When `interior` is set, glyphs of the cluster with the lowest value are not marked.
+    fn set_glyph_flags(
+        &mut self,
+        mask: Mask,
+        start: usize,
+        end: usize,
+        interior: bool,
+        from_out_buffer: bool,
+    ) {
+        let end = end.min(self.len);
+
+        if interior && !from_out_buffer && end.saturating_sub(start) < 2 {
+            return;
+        }
+
+        if !from_out_buffer || !self.have_output {
+            let cluster = if interior {
+                Some(Self::find_min_cluster(&self.info, start, end, core::u32::MAX))
+            } else {
+                None
+            };
+
+            if Self::set_glyph_flags_impl(&mut self.info, start, end, cluster, mask) {
+                self.scratch_flags |= BufferScratchFlags::HAS_GLYPH_FLAGS;
+            }
+        } else {
+            assert!(start <= self.out_len);
+            assert!(self.idx <= end);
+
+            let cluster = if interior {
+                let cluster = Self::find_min_cluster(self.out_info(), start, self.out_len, core::u32::MAX);
+                Some(Self::find_min_cluster(&self.info, self.idx, end, cluster))
+            } else {
+                None
+            };
+
+            let idx = self.idx;
+            let out_len = self.out_len;
+            let changed1 = Self::set_glyph_flags_impl(self.out_info_mut(), start, out_len, cluster, mask);
+            let changed2 = Self::set_glyph_flags_impl(&mut self.info, idx, end, cluster, mask);
 
-        if unsafe_to_break1 || unsafe_to_break2 {
-            self.scratch_flags |= BufferScratchFlags::HAS_UNSAFE_TO_BREAK;
+            if changed1 || changed2 {
+                self.scratch_flags |= BufferScratchFlags::HAS_GLYPH_FLAGS;
+            }
         }
     }
 
@@ -1319,17 +1631,13 @@ impl Buffer {
 
     pub fn set_cluster(info: &mut GlyphInfo, cluster: u32, mask: Mask) {
         if info.cluster != cluster {
-            if mask & glyph_flag::UNSAFE_TO_BREAK != 0 {
-                info.mask |= glyph_flag::UNSAFE_TO_BREAK;
-            } else {
-                info.mask &= !glyph_flag::UNSAFE_TO_BREAK;
-            }
+            info.mask = (info.mask & !glyph_flag::DEFINED) | (mask & glyph_flag::DEFINED);
         }
 
         info.cluster = cluster;
     }
 
-    fn _unsafe_to_break_find_min_cluster(info: &[GlyphInfo], start: usize, end: usize, mut cluster: u32) -> u32 {
+    fn find_min_cluster(info: &[GlyphInfo], start: usize, end: usize, mut cluster: u32) -> u32 {
         for glyph_info in &info[start..end] {
             cluster = core::cmp::min(cluster, glyph_info.cluster);
         }
@@ -1337,16 +1645,23 @@ impl Buffer {
         cluster
     }
 
-    fn _unsafe_to_break_set_mask(info: &mut [GlyphInfo], start: usize, end: usize, cluster: u32) -> bool {
-        let mut unsafe_to_break = false;
+    // Marks all glyphs when `cluster` is `None`.
+    fn set_glyph_flags_impl(
+        info: &mut [GlyphInfo],
+        start: usize,
+        end: usize,
+        cluster: Option<u32>,
+        mask: Mask,
+    ) -> bool {
+        let mut changed = false;
         for glyph_info in &mut info[start..end] {
-            if glyph_info.cluster != cluster {
-                unsafe_to_break = true;
-                glyph_info.mask |= glyph_flag::UNSAFE_TO_BREAK;
+            if Some(glyph_info.cluster) != cluster {
+                changed = true;
+                glyph_info.mask |= mask;
             }
         }
 
-        unsafe_to_break
+        changed
     }
 
     /// Checks that buffer contains no elements.
@@ -1355,10 +1670,125 @@ impl Buffer {
     }
 
     fn push_str(&mut self, text: &str) {
-        self.ensure(self.len + text.chars().count());
+        self.push_str_with_context(text, 0..text.len());
+    }
+
+    fn push_str_with_context(&mut self, text: &str, item: core::ops::Range<usize>) {
+        assert!(text.is_char_boundary(item.start) && text.is_char_boundary(item.end));
+
+        self.push_decoded(
+            |range| {
+                // The pre-context range can start in the middle of a char.
+                let start = (range.start..range.end)
+                    .find(|i| text.is_char_boundary(*i))
+                    .unwrap_or(range.end);
+                text[start..range.end].char_indices().map(move |(i, c)| (start - range.start + i, c))
+            },
+            text.len(),
+            item,
+            ClusterUnit::Utf8,
+            ClusterUnit::Utf8,
+        );
+    }
+
+    /// Creates a buffer with the same settings and a `range` of the text,
+    /// using the rest of the text as context.
+    pub(crate) fn sub_buffer(&self, range: core::ops::Range<usize>, direction: Direction) -> Buffer {
+        let mut buffer = Buffer::new();
+        buffer.flags = self.flags;
+        if range.start != 0 {
+            buffer.flags.remove(BufferFlags::BEGINNING_OF_TEXT);
+        }
+        if range.end != self.len {
+            buffer.flags.remove(BufferFlags::END_OF_TEXT);
+        }
+
+        buffer.cluster_level = self.cluster_level;
+        buffer.replacement = self.replacement;
+        buffer.invisible = self.invisible;
+        buffer.not_found = self.not_found;
+        buffer.direction = direction;
+        buffer.script = self.script;
+        buffer.language = self.language.clone();
+
+        buffer.ensure(range.len());
+        for info in &self.info[range.clone()] {
+            buffer.add(info.glyph_id, info.cluster);
+        }
+
+        let before = self.info[..range.start].iter().rev()
+            .map(GlyphInfo::as_char)
+            .chain(self.context[0][..self.context_len[0]].iter().copied());
+        for (i, c) in before.take(CONTEXT_LENGTH).enumerate() {
+            buffer.context[0][i] = c;
+            buffer.context_len[0] = i + 1;
+        }
+
+        let after = self.info[range.end..self.len].iter()
+            .map(GlyphInfo::as_char)
+            .chain(self.context[1][..self.context_len[1]].iter().copied());
+        for (i, c) in after.take(CONTEXT_LENGTH).enumerate() {
+            buffer.context[1][i] = c;
+            buffer.context_len[1] = i + 1;
+        }
+
+        buffer
+    }
 
-        for (i, c) in text.char_indices() {
-            self.add(c as u32, i as u32);
+    fn push_decoded<'a, D, I>(
+        &mut self,
+        decode: D,
+        text_len: usize,
+        item: core::ops::Range<usize>,
+        text_unit: ClusterUnit,
+        cluster_unit: ClusterUnit,
+    )
+    where
+        D: Fn(core::ops::Range<usize>) -> I,
+        I: Iterator<Item = (usize, char)> + 'a,
+    {
+        assert!(item.start <= item.end && item.end <= text_len);
+
+        // The longest char in code units of the text.
+        let max_char_len = text_unit.char_len(char::MAX);
+
+        // Pre-context is only set on the first push.
+        if self.len == 0 && item.start > 0 {
+            // Enough to get full context, even if we start in the middle of a char.
+            let start = item.start.saturating_sub(CONTEXT_LENGTH * max_char_len + max_char_len - 1);
+            let mut context = ['\0'; CONTEXT_LENGTH];
+            let mut count = 0;
+            for (_, c) in decode(start..item.start) {
+                context[count % CONTEXT_LENGTH] = c;
+                count += 1;
+            }
+
+            self.context_len[0] = count.min(CONTEXT_LENGTH);
+            for i in 0..self.context_len[0] {
+                self.context[0][i] = context[(count - 1 - i) % CONTEXT_LENGTH];
+            }
+        }
+
+        let mut cluster = if text_unit == cluster_unit {
+            item.start
+        } else {
+            decode(0..item.start).map(|(_, c)| cluster_unit.char_len(c)).sum()
+        };
+
+        self.ensure(self.len + item.len());
+        for (offset, c) in decode(item.clone()) {
+            if text_unit == cluster_unit {
+                cluster = item.start + offset;
+            }
+
+            self.add(c as u32, cluster as u32);
+            cluster += cluster_unit.char_len(c);
+        }
+
+        self.context_len[1] = 0;
+        for (_, c) in decode(item.end..text_len).take(CONTEXT_LENGTH) {
+            self.context[1][self.context_len[1]] = c;
+            self.context_len[1] += 1;
         }
     }
 
@@ -1489,13 +1919,43 @@ bitflags::bitflags! {
 
 
 bitflags::bitflags! {
+    /// Flags that control how the buffer content is treated.
     #[derive(Default)]
     pub struct BufferFlags: u32 {
+        /// Indicates that the buffer content is at the beginning of text.
+        ///
+        /// When not set, the buffer is assumed to be a part of a larger text
+        /// and a dotted circle will not be inserted before a leading mark.
         const BEGINNING_OF_TEXT             = 1 << 1;
+        /// Indicates that the buffer content is at the end of text.
         const END_OF_TEXT                   = 1 << 2;
+        /// Keep default ignorable characters visible instead of replacing them
+        /// with an invisible glyph.
         const PRESERVE_DEFAULT_IGNORABLES   = 1 << 3;
+        /// Remove default ignorable characters from the output
+        /// instead of replacing them with an invisible glyph.
         const REMOVE_DEFAULT_IGNORABLES     = 1 << 4;
+        /// Do not insert a dotted circle glyph before marks that cannot be
+        /// attached to a base, like an orphan mark at the beginning of text
+        /// or a broken cluster.
         const DO_NOT_INSERT_DOTTED_CIRCLE   = 1 << 5;
+        /// Compute the `unsafe_to_concat` glyph flag.
+        ///
+        /// Computing it has a performance cost, therefore it's disabled by default.
+        const PRODUCE_UNSAFE_TO_CONCAT      = 1 << 6;
+        /// Compute the `safe_to_insert_tatweel` glyph flag.
+        ///
+        /// When not set, positions that are safe to insert a tatweel
+        /// are marked as unsafe to break instead.
+        const PRODUCE_SAFE_TO_INSERT_TATWEEL = 1 << 7;
+        /// Set sideways characters of top-to-bottom text.
+        ///
+        /// Runs of characters that are [rotated](crate::VerticalOrientation::Rotated)
+        /// are shaped horizontally and rotated 90 degrees clockwise, with the
+        /// em box centered on the vertical line. Their glyph positions are marked as `sideways`.
+        /// Other characters are shaped upright. [`shape_with_plan`](crate::shape_with_plan)
+        /// returns an error instead, since the runs need different plans.
+        const ROTATE_SIDEWAYS               = 1 << 8;
     }
 }
 
@@ -1507,7 +1967,7 @@ bitflags::bitflags! {
         const HAS_DEFAULT_IGNORABLES    = 0x00000002;
         const HAS_SPACE_FALLBACK        = 0x00000004;
         const HAS_GPOS_ATTACHMENT       = 0x00000008;
-        const HAS_UNSAFE_TO_BREAK       = 0x00000010;
+        const HAS_GLYPH_FLAGS           = 0x00000010;
         const HAS_CGJ                   = 0x00000020;
 
         // Reserved for complex shapers' internal use.
@@ -1539,6 +1999,39 @@ bitflags::bitflags! {
     }
 }
 
+/// A glyph buffer serialization format.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SerializeFormat {
+    /// The text format, like `a=0+486|b=1+500`.
+    Text,
+    /// The JSON format, like `[{"g":"a","cl":0,"dx":0,"dy":0,"ax":486,"ay":0}]`.
+    Json,
+}
+
+impl Default for SerializeFormat {
+    #[inline]
+    fn default() -> Self {
+        SerializeFormat::Text
+    }
+}
+
+bitflags::bitflags! {
+    /// Differences between two glyph buffers, see [`GlyphBuffer::diff`].
+    #[derive(Default)]
+    pub struct BufferDiffFlags: u8 {
+        /// Buffers have a different number of glyphs.
+        const LENGTH_MISMATCH       = 0b00000001;
+        /// Glyph indices differ.
+        const GLYPH_MISMATCH        = 0b00000010;
+        /// Clusters differ.
+        const CLUSTER_MISMATCH      = 0b00000100;
+        /// Glyph flags differ.
+        const GLYPH_FLAGS_MISMATCH  = 0b00001000;
+        /// Glyph positions differ by more than the allowed fuzz.
+        const POSITION_MISMATCH     = 0b00010000;
+    }
+}
+
 
 /// A buffer that contains an input string ready for shaping.
 pub struct UnicodeBuffer(pub(crate) Buffer);
@@ -1571,6 +2064,94 @@ impl UnicodeBuffer {
         self.0.push_str(str);
     }
 
+    /// Pushes a part of a string to a buffer, using the rest of the string as context.
+    ///
+    /// Only the `item` byte range of `text` is added to the buffer.
+    /// Up to 5 characters before and after the range are stored as the pre-
+    /// and post-context, which are used by shapers to properly handle run boundaries,
+    /// like Arabic joining. The pre-context is only set when the buffer is empty.
+    ///
+    /// Clusters are byte offsets into `text`, not into the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `item` is out of bounds or doesn't lie on char boundaries.
+    #[inline]
+    pub fn push_str_with_context(&mut self, text: &str, item: core::ops::Range<usize>) {
+        self.0.push_str_with_context(text, item);
+    }
+
+    /// Pushes a part of UTF-8 text to a buffer, using the rest of the text as context.
+    ///
+    /// Unlike [`push_str_with_context`](Self::push_str_with_context),
+    /// accepts invalid UTF-8. Each invalid sequence is replaced with
+    /// the [replacement codepoint](Self::set_replacement_codepoint).
+    ///
+    /// `item` is a range of bytes. Clusters are offsets into `text`
+    /// in `cluster_unit`s. When `cluster_unit` is not [`ClusterUnit::Utf8`],
+    /// the text before `item` is decoded to calculate them.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `item` is out of bounds.
+    pub fn push_utf8(&mut self, text: &[u8], item: core::ops::Range<usize>, cluster_unit: ClusterUnit) {
+        let replacement = self.0.replacement;
+        self.0.push_decoded(
+            |range| decode_utf8(&text[range], replacement),
+            text.len(),
+            item,
+            ClusterUnit::Utf8,
+            cluster_unit,
+        );
+    }
+
+    /// Pushes a part of UTF-16 text to a buffer, using the rest of the text as context.
+    ///
+    /// Each unpaired surrogate is replaced with
+    /// the [replacement codepoint](Self::set_replacement_codepoint).
+    ///
+    /// `item` is a range of UTF-16 code units. Clusters are offsets into `text`
+    /// in `cluster_unit`s. When `cluster_unit` is not [`ClusterUnit::Utf16`],
+    /// the text before `item` is decoded to calculate them.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `item` is out of bounds.
+    pub fn push_utf16(&mut self, text: &[u16], item: core::ops::Range<usize>, cluster_unit: ClusterUnit) {
+        let replacement = self.0.replacement;
+        self.0.push_decoded(
+            |range| decode_utf16(&text[range], replacement),
+            text.len(),
+            item,
+            ClusterUnit::Utf16,
+            cluster_unit,
+        );
+    }
+
+    /// Pushes a part of text as Unicode codepoints to a buffer,
+    /// using the rest of the text as context.
+    ///
+    /// Each surrogate or out of range value is replaced with
+    /// the [replacement codepoint](Self::set_replacement_codepoint).
+    ///
+    /// `item` is a range of codepoints. Clusters are offsets into `text`
+    /// in `cluster_unit`s. When `cluster_unit` is not [`ClusterUnit::Codepoint`],
+    /// the text before `item` is decoded to calculate them.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `item` is out of bounds.
+    pub fn push_codepoints(&mut self, text: &[u32], item: core::ops::Range<usize>, cluster_unit: ClusterUnit) {
+        let replacement = self.0.replacement;
+        self.0.push_decoded(
+            |range| decode_codepoints(&text[range], replacement),
+            text.len(),
+            item,
+            ClusterUnit::Codepoint,
+            cluster_unit,
+        );
+    }
+
     /// Appends a character to a buffer with the given cluster value.
     #[inline]
     pub fn add(&mut self, codepoint: char, cluster: u32) {
@@ -1620,6 +2201,20 @@ impl UnicodeBuffer {
         self.0.guess_segment_properties()
     }
 
+    /// Set the buffer flags.
+    ///
+    /// Flags are not reset by `clear`.
+    #[inline]
+    pub fn set_flags(&mut self, flags: BufferFlags) {
+        self.0.flags = flags;
+    }
+
+    /// Get the buffer flags.
+    #[inline]
+    pub fn flags(&self) -> BufferFlags {
+        self.0.flags
+    }
+
     /// Set the cluster level of the buffer.
     #[inline]
     pub fn set_cluster_level(&mut self, cluster_level: BufferClusterLevel) {
@@ -1632,6 +2227,55 @@ impl UnicodeBuffer {
         self.0.cluster_level
     }
 
+    /// Set the codepoint that replaces invalid input.
+    ///
+    /// Used by [`push_utf8`](Self::push_utf8), [`push_utf16`](Self::push_utf16)
+    /// and [`push_codepoints`](Self::push_codepoints). The default is U+FFFD.
+    /// Not reset by `clear`.
+    #[inline]
+    pub fn set_replacement_codepoint(&mut self, replacement: char) {
+        self.0.replacement = replacement;
+    }
+
+    /// Get the codepoint that replaces invalid input.
+    #[inline]
+    pub fn replacement_codepoint(&self) -> char {
+        self.0.replacement
+    }
+
+    /// Set the glyph that replaces hidden default ignorable characters.
+    ///
+    /// When `None`, the space glyph is used, and default ignorables are
+    /// removed if the face doesn't have one. Ignored when
+    /// [`BufferFlags::REMOVE_DEFAULT_IGNORABLES`] or
+    /// [`BufferFlags::PRESERVE_DEFAULT_IGNORABLES`] is set.
+    /// Not reset by `clear`.
+    #[inline]
+    pub fn set_invisible_glyph(&mut self, glyph: Option<GlyphId>) {
+        self.0.invisible = glyph;
+    }
+
+    /// Get the glyph that replaces hidden default ignorable characters.
+    #[inline]
+    pub fn invisible_glyph(&self) -> Option<GlyphId> {
+        self.0.invisible
+    }
+
+    /// Set the glyph that is used for characters not supported by the face.
+    ///
+    /// The default is glyph 0, i.e. `.notdef`.
+    /// Not reset by `clear`.
+    #[inline]
+    pub fn set_not_found_glyph(&mut self, glyph: GlyphId) {
+        self.0.not_found = glyph;
+    }
+
+    /// Get the glyph that is used for characters not supported by the face.
+    #[inline]
+    pub fn not_found_glyph(&self) -> GlyphId {
+        self.0.not_found
+    }
+
     /// Resets clusters.
     #[inline]
     pub fn reset_clusters(&mut self) {
@@ -1643,6 +2287,131 @@ impl UnicodeBuffer {
     pub fn clear(&mut self) {
         self.0.clear()
     }
+
+    /// Converts the buffer content into a string.
+    ///
+    /// Uses the harfbuzz codepoint format followed by the segment properties that are set,
+    /// like `<U+0627=0|U+0644=1> direction=rtl script=Arab language=ar`.
+    /// [`deserialize`](Self::deserialize) parses it back.
+    pub fn serialize(&self) -> String {
+        self.serialize_impl().unwrap_or_default()
+    }
+
+    fn serialize_impl(&self) -> Result<String, core::fmt::Error> {
+        use core::fmt::Write;
+
+        let mut s = String::with_capacity(64);
+
+        s.push('<');
+        for (i, info) in self.0.info[..self.0.len].iter().enumerate() {
+            if i != 0 {
+                s.push('|');
+            }
+
+            write!(&mut s, "U+{:04X}={}", info.glyph_id, info.cluster)?;
+        }
+        s.push('>');
+
+        let direction = match self.0.direction {
+            Direction::Invalid => None,
+            Direction::LeftToRight => Some("ltr"),
+            Direction::RightToLeft => Some("rtl"),
+            Direction::TopToBottom => Some("ttb"),
+            Direction::BottomToTop => Some("btt"),
+        };
+
+        if let Some(direction) = direction {
+            write!(&mut s, " direction={}", direction)?;
+        }
+
+        if let Some(script) = self.0.script {
+            write!(&mut s, " script={}", script.tag())?;
+        }
+
+        if let Some(ref language) = self.0.language {
+            write!(&mut s, " language={}", language.as_str())?;
+        }
+
+        Ok(s)
+    }
+
+    /// Parses a buffer from a string produced by [`serialize`](Self::serialize).
+    ///
+    /// Also accepts the plain harfbuzz codepoint format, like `<U+0041=0|U+0042=1>`.
+    /// The angle brackets are optional. A codepoint without a cluster
+    /// gets its index as the cluster.
+    pub fn deserialize(text: &str) -> Result<UnicodeBuffer, &'static str> {
+        let mut buffer = UnicodeBuffer::new();
+        let mut s = TextParser::new(text);
+        s.skip_spaces();
+        let bracket = s.consume_byte(b'<').is_some();
+
+        loop {
+            s.skip_spaces();
+            if s.at_end() || s.curr_byte() == Some(b'>') {
+                break;
+            }
+
+            s.consume_byte(b'U').ok_or("expected a codepoint")?;
+            s.consume_byte(b'+').ok_or("expected a codepoint")?;
+            let c = s.consume_hex_u32().and_then(char::from_u32).ok_or("invalid codepoint")?;
+
+            let mut cluster = buffer.len() as u32;
+            if s.consume_byte(b'=').is_some() {
+                cluster = s.consume_u32().ok_or("invalid cluster")?;
+            }
+
+            buffer.add(c, cluster);
+
+            s.skip_spaces();
+            if s.consume_byte(b'|').is_none() {
+                break;
+            }
+        }
+
+        if bracket {
+            s.consume_byte(b'>').ok_or("expected '>'")?;
+        }
+
+        loop {
+            s.skip_spaces();
+            if s.at_end() {
+                break;
+            }
+
+            let key = s.consume_bytes(|c| c.is_ascii_alphabetic());
+            s.consume_byte(b'=').ok_or("expected a segment property")?;
+            let value = s.consume_bytes(|c| !c.is_ascii_whitespace());
+            match key {
+                "direction" => buffer.set_direction(value.parse()?),
+                "script" => buffer.set_script(value.parse()?),
+                "language" => buffer.set_language(value.parse()?),
+                _ => return Err("unknown segment property"),
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    /// Sets a function that receives trace messages during shaping.
+    ///
+    /// Messages are like `start table GSUB`, `start lookup 3 feature 'liga'`
+    /// or `start reordering indic initial`, each followed by a matching `end` message.
+    /// The function also gets a read-only view of the buffer at that point.
+    /// Returning `false` on a `start` message skips that step.
+    ///
+    /// The function is kept when the buffer is cleared or shaped.
+    /// Available only with the `trace` feature.
+    #[cfg(feature = "trace")]
+    pub fn set_message_func(&mut self, func: impl FnMut(&BufferView, &str) -> bool + Send + Sync + 'static) {
+        self.0.message_func = Some(alloc::boxed::Box::new(func));
+    }
+
+    /// Removes the message function.
+    #[cfg(feature = "trace")]
+    pub fn remove_message_func(&mut self) {
+        self.0.message_func = None;
+    }
 }
 
 impl core::fmt::Debug for UnicodeBuffer {
@@ -1652,6 +2421,7 @@ impl core::fmt::Debug for UnicodeBuffer {
             .field("language", &self.language())
             .field("script", &self.script())
             .field("cluster_level", &self.cluster_level())
+            .field("flags", &self.flags())
             .finish()
     }
 }
@@ -1705,16 +2475,333 @@ impl GlyphBuffer {
 
     /// Converts the glyph buffer content into a string.
     pub fn serialize(&self, face: &Face, flags: SerializeFlags) -> String {
-        self.serialize_impl(face, flags).unwrap_or_default()
+        self.serialize_with_format(face, SerializeFormat::Text, flags)
+    }
+
+    /// Converts the glyph buffer content into a string of the given format.
+    ///
+    /// All formats honor the same `flags`.
+    pub fn serialize_with_format(&self, face: &Face, format: SerializeFormat, flags: SerializeFlags) -> String {
+        Self::serialize_impl(face, format, flags, self.glyph_infos(), self.glyph_positions()).unwrap_or_default()
+    }
+
+    /// Compares the buffer with a reference buffer.
+    ///
+    /// Returns empty flags when the buffers are equal. Positions are equal when they
+    /// differ by at most `position_fuzz` units. Glyphs are compared only when both
+    /// buffers have the same length; use [`diff_glyphs`](Self::diff_glyphs)
+    /// to find out which glyphs differ.
+    pub fn diff(&self, reference: &GlyphBuffer, position_fuzz: u32) -> BufferDiffFlags {
+        if self.len() != reference.len() {
+            return BufferDiffFlags::LENGTH_MISMATCH;
+        }
+
+        self.diff_glyphs(reference, position_fuzz)
+            .into_iter()
+            .fold(BufferDiffFlags::empty(), |flags, (_, glyph_flags)| flags | glyph_flags)
+    }
+
+    /// Compares the buffer with a reference buffer glyph by glyph.
+    ///
+    /// Returns indices and differences of all glyphs that differ, in order.
+    /// When the buffers have a different length, only the common glyphs are compared.
+    pub fn diff_glyphs(&self, reference: &GlyphBuffer, position_fuzz: u32) -> Vec<(usize, BufferDiffFlags)> {
+        let position_equal = |a: i32, b: i32| (i64::from(a) - i64::from(b)).abs() <= i64::from(position_fuzz);
+
+        let infos = self.glyph_infos().iter().zip(reference.glyph_infos());
+        let positions = self.glyph_positions().iter().zip(reference.glyph_positions());
+        let mut diffs = Vec::new();
+        for (i, ((info, ref_info), (pos, ref_pos))) in infos.zip(positions).enumerate() {
+            let mut flags = BufferDiffFlags::empty();
+            if info.glyph_id != ref_info.glyph_id {
+                flags |= BufferDiffFlags::GLYPH_MISMATCH;
+            }
+
+            if info.cluster != ref_info.cluster {
+                flags |= BufferDiffFlags::CLUSTER_MISMATCH;
+            }
+
+            if (info.mask ^ ref_info.mask) & glyph_flag::DEFINED != 0 {
+                flags |= BufferDiffFlags::GLYPH_FLAGS_MISMATCH;
+            }
+
+            if !position_equal(pos.x_advance, ref_pos.x_advance)
+                || !position_equal(pos.y_advance, ref_pos.y_advance)
+                || !position_equal(pos.x_offset, ref_pos.x_offset)
+                || !position_equal(pos.y_offset, ref_pos.y_offset)
+            {
+                flags |= BufferDiffFlags::POSITION_MISMATCH;
+            }
+
+            if !flags.is_empty() {
+                diffs.push((i, flags));
+            }
+        }
+
+        diffs
+    }
+
+    /// Parses a glyph buffer from the text format produced by [`serialize`](Self::serialize).
+    ///
+    /// Glyphs can be given by name, as `gidN` or as a glyph index, so both
+    /// the default output and the [`SerializeFlags::NO_GLYPH_NAMES`] one are accepted.
+    /// Missing clusters, positions and advances are set to zero.
+    /// The square brackets of the harfbuzz output are optional. Glyph extents are ignored.
+    pub fn deserialize(face: &Face, text: &str) -> Result<GlyphBuffer, &'static str> {
+        Self::deserialize_with_format(face, text, SerializeFormat::Text)
+    }
+
+    /// Parses a glyph buffer from a string of the given format.
+    ///
+    /// See [`deserialize`](Self::deserialize) for details.
+    pub fn deserialize_with_format(
+        face: &Face,
+        text: &str,
+        format: SerializeFormat,
+    ) -> Result<GlyphBuffer, &'static str> {
+        match format {
+            SerializeFormat::Text => Self::deserialize_text(face, text),
+            SerializeFormat::Json => Self::deserialize_json(face, text),
+        }
+    }
+
+    fn deserialize_text(face: &Face, text: &str) -> Result<GlyphBuffer, &'static str> {
+        let mut buffer = Buffer::new();
+        let mut s = TextParser::new(text);
+        s.skip_spaces();
+        let bracket = s.consume_byte(b'[').is_some();
+
+        loop {
+            s.skip_spaces();
+            if s.at_end() || s.curr_byte() == Some(b']') {
+                break;
+            }
+
+            let name = s.consume_bytes(|c| {
+                !c.is_ascii_whitespace() && !matches!(c, b'=' | b'@' | b'+' | b'#' | b'<' | b'|' | b']')
+            });
+            let glyph = parse_glyph(face, name).ok_or("invalid glyph")?;
+
+            let mut info = GlyphInfo { glyph_id: u32::from(glyph.0), ..GlyphInfo::default() };
+            let mut pos = GlyphPosition::default();
+
+            if s.consume_byte(b'=').is_some() {
+                info.cluster = s.consume_u32().ok_or("invalid cluster")?;
+            }
+
+            if s.consume_byte(b'@').is_some() {
+                pos.x_offset = s.consume_i32().ok_or("invalid offset")?;
+                s.consume_byte(b',').ok_or("invalid offset")?;
+                pos.y_offset = s.consume_i32().ok_or("invalid offset")?;
+            }
+
+            if s.consume_byte(b'+').is_some() {
+                pos.x_advance = s.consume_i32().ok_or("invalid advance")?;
+                if s.consume_byte(b',').is_some() {
+                    pos.y_advance = s.consume_i32().ok_or("invalid advance")?;
+                }
+            }
+
+            if s.consume_byte(b'#').is_some() {
+                info.mask = s.consume_hex_u32().ok_or("invalid glyph flags")? & glyph_flag::DEFINED;
+            }
+
+            if s.consume_byte(b'<').is_some() {
+                s.skip_bytes(|c| c != b'>');
+                s.consume_byte(b'>').ok_or("invalid glyph extents")?;
+            }
+
+            buffer.info.push(info);
+            buffer.pos.push(pos);
+
+            s.skip_spaces();
+            if s.consume_byte(b'|').is_none() {
+                break;
+            }
+        }
+
+        if bracket {
+            s.consume_byte(b']').ok_or("expected ']'")?;
+        }
+
+        s.skip_spaces();
+        if !s.at_end() {
+            return Err("unexpected trailing characters");
+        }
+
+        buffer.len = buffer.info.len();
+        buffer.have_positions = true;
+        Ok(GlyphBuffer(buffer))
+    }
+
+    fn deserialize_json(face: &Face, text: &str) -> Result<GlyphBuffer, &'static str> {
+        let mut buffer = Buffer::new();
+        let mut s = TextParser::new(text);
+        s.skip_spaces();
+        s.consume_byte(b'[').ok_or("expected '['")?;
+        s.skip_spaces();
+
+        if s.consume_byte(b']').is_none() {
+            loop {
+                s.skip_spaces();
+                s.consume_byte(b'{').ok_or("expected '{'")?;
+
+                let mut glyph = None;
+                let mut info = GlyphInfo::default();
+                let mut pos = GlyphPosition::default();
+                loop {
+                    s.skip_spaces();
+                    let key = consume_json_string(&mut s).ok_or("expected a key")?;
+                    s.skip_spaces();
+                    s.consume_byte(b':').ok_or("expected ':'")?;
+                    s.skip_spaces();
+
+                    match key.as_str() {
+                        "g" => {
+                            glyph = if s.curr_byte() == Some(b'"') {
+                                consume_json_string(&mut s).and_then(|name| parse_glyph(face, &name))
+                            } else {
+                                s.consume_u32().and_then(|index| u16::try_from(index).ok()).map(GlyphId)
+                            };
+                            glyph.ok_or("invalid glyph")?;
+                        }
+                        "cl" => info.cluster = s.consume_u32().ok_or("invalid cluster")?,
+                        "dx" => pos.x_offset = s.consume_i32().ok_or("invalid offset")?,
+                        "dy" => pos.y_offset = s.consume_i32().ok_or("invalid offset")?,
+                        "ax" => pos.x_advance = s.consume_i32().ok_or("invalid advance")?,
+                        "ay" => pos.y_advance = s.consume_i32().ok_or("invalid advance")?,
+                        "fl" => info.mask = s.consume_u32().ok_or("invalid glyph flags")? & glyph_flag::DEFINED,
+                        // Glyph extents are ignored.
+                        _ => { s.consume_i32().ok_or("invalid value")?; }
+                    }
+
+                    s.skip_spaces();
+                    if s.consume_byte(b',').is_none() {
+                        break;
+                    }
+                }
+
+                s.consume_byte(b'}').ok_or("expected '}'")?;
+                info.glyph_id = u32::from(glyph.ok_or("missing glyph")?.0);
+                buffer.info.push(info);
+                buffer.pos.push(pos);
+
+                s.skip_spaces();
+                if s.consume_byte(b',').is_none() {
+                    break;
+                }
+            }
+
+            s.consume_byte(b']').ok_or("expected ']'")?;
+        }
+
+        s.skip_spaces();
+        if !s.at_end() {
+            return Err("unexpected trailing characters");
+        }
+
+        buffer.len = buffer.info.len();
+        buffer.have_positions = true;
+        Ok(GlyphBuffer(buffer))
+    }
+
+    fn serialize_impl(
+        face: &Face,
+        format: SerializeFormat,
+        flags: SerializeFlags,
+        info: &[GlyphInfo],
+        pos: &[GlyphPosition],
+    ) -> Result<String, core::fmt::Error> {
+        match format {
+            SerializeFormat::Text => Self::serialize_text(face, flags, info, pos),
+            SerializeFormat::Json => Self::serialize_json(face, flags, info, pos),
+        }
+    }
+
+    fn serialize_json(
+        face: &Face,
+        flags: SerializeFlags,
+        info: &[GlyphInfo],
+        pos: &[GlyphPosition],
+    ) -> Result<String, core::fmt::Error> {
+        use core::fmt::Write;
+
+        let mut s = String::with_capacity(64);
+        s.push('[');
+
+        let mut x = 0;
+        let mut y = 0;
+        for (i, (info, pos)) in info.iter().zip(pos).enumerate() {
+            if i != 0 {
+                s.push(',');
+            }
+
+            s.push_str("{\"g\":");
+            if !flags.contains(SerializeFlags::NO_GLYPH_NAMES) {
+                s.push('"');
+                match face.glyph_name(info.as_glyph()) {
+                    Some(name) => {
+                        for c in name.chars() {
+                            if matches!(c, '"' | '\\') {
+                                s.push('\\');
+                            }
+                            s.push(c);
+                        }
+                    }
+                    None => write!(&mut s, "gid{}", info.glyph_id)?,
+                }
+                s.push('"');
+            } else {
+                write!(&mut s, "{}", info.glyph_id)?;
+            }
+
+            if !flags.contains(SerializeFlags::NO_CLUSTERS) {
+                write!(&mut s, ",\"cl\":{}", info.cluster)?;
+            }
+
+            if !flags.contains(SerializeFlags::NO_POSITIONS) {
+                write!(&mut s, ",\"dx\":{},\"dy\":{}", x + pos.x_offset, y + pos.y_offset)?;
+
+                if !flags.contains(SerializeFlags::NO_ADVANCES) {
+                    write!(&mut s, ",\"ax\":{},\"ay\":{}", pos.x_advance, pos.y_advance)?;
+                }
+            }
+
+            if flags.contains(SerializeFlags::GLYPH_FLAGS) && info.mask & glyph_flag::DEFINED != 0 {
+                write!(&mut s, ",\"fl\":{}", info.mask & glyph_flag::DEFINED)?;
+            }
+
+            if flags.contains(SerializeFlags::GLYPH_EXTENTS) {
+                let extents = face.glyph_extents(info.as_glyph()).unwrap_or_default();
+                write!(
+                    &mut s,
+                    ",\"xb\":{},\"yb\":{},\"w\":{},\"h\":{}",
+                    extents.x_bearing, extents.y_bearing, extents.width, extents.height,
+                )?;
+            }
+
+            s.push('}');
+
+            if flags.contains(SerializeFlags::NO_ADVANCES) {
+                x += pos.x_advance;
+                y += pos.y_advance;
+            }
+        }
+
+        s.push(']');
+        Ok(s)
     }
 
-    fn serialize_impl(&self, face: &Face, flags: SerializeFlags) -> Result<String, core::fmt::Error> {
+    fn serialize_text(
+        face: &Face,
+        flags: SerializeFlags,
+        info: &[GlyphInfo],
+        pos: &[GlyphPosition],
+    ) -> Result<String, core::fmt::Error> {
         use core::fmt::Write;
 
         let mut s = String::with_capacity(64);
 
-        let info = self.glyph_infos();
-        let pos = self.glyph_positions();
         let mut x = 0;
         let mut y = 0;
         for (info, pos) in info.iter().zip(pos) {
@@ -1772,6 +2859,121 @@ impl GlyphBuffer {
     }
 }
 
+/// Resolves a glyph by name, `gidN` or index, like `hb_font_glyph_from_string`.
+fn parse_glyph(face: &Face, name: &str) -> Option<GlyphId> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if let Some(glyph) = face.glyph_index_by_name(name) {
+        return Some(glyph);
+    }
+
+    let index = name.strip_prefix("gid").unwrap_or(name);
+    index.parse::<u16>().ok().map(GlyphId)
+}
+
+/// Parses a JSON string with the escapes that the serializer produces.
+fn consume_json_string(s: &mut TextParser) -> Option<String> {
+    s.consume_byte(b'"')?;
+
+    let mut string = String::new();
+    loop {
+        string.push_str(s.consume_bytes(|c| c != b'"' && c != b'\\'));
+        let c = s.curr_byte()?;
+        s.advance(1);
+        if c == b'"' {
+            return Some(string);
+        }
+
+        let c = s.curr_byte()?;
+        if !matches!(c, b'"' | b'\\' | b'/') {
+            return None;
+        }
+
+        s.advance(1);
+        string.push(char::from(c));
+    }
+}
+
+/// A read-only view of a buffer during shaping.
+///
+/// Passed to the message function, see [`UnicodeBuffer::set_message_func`].
+#[cfg(feature = "trace")]
+pub struct BufferView<'a> {
+    buffer: &'a Buffer,
+    face: &'a Face<'a>,
+}
+
+#[cfg(feature = "trace")]
+impl BufferView<'_> {
+    /// Returns the number of glyphs, or of codepoints before they are mapped to glyphs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buffer.len
+    }
+
+    /// Returns `true` if the buffer contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the buffer direction.
+    #[inline]
+    pub fn direction(&self) -> Direction {
+        self.buffer.direction
+    }
+
+    /// Returns the buffer script.
+    #[inline]
+    pub fn script(&self) -> Option<Script> {
+        self.buffer.script
+    }
+
+    /// Returns the buffer language.
+    #[inline]
+    pub fn language(&self) -> Option<&Language> {
+        self.buffer.language.as_ref()
+    }
+
+    /// Returns the face being shaped with.
+    #[inline]
+    pub fn face(&self) -> &Face<'_> {
+        self.face
+    }
+
+    /// Get the glyph infos.
+    #[inline]
+    pub fn glyph_infos(&self) -> &[GlyphInfo] {
+        &self.buffer.info[..self.buffer.len]
+    }
+
+    /// Get the glyph positions.
+    ///
+    /// Empty until positioning has started.
+    #[inline]
+    pub fn glyph_positions(&self) -> &[GlyphPosition] {
+        if self.buffer.have_positions {
+            &self.buffer.pos[..self.buffer.len]
+        } else {
+            &[]
+        }
+    }
+
+    /// Converts the buffer content into a string.
+    ///
+    /// Positions are omitted until positioning has started.
+    pub fn serialize(&self, mut flags: SerializeFlags) -> String {
+        if !self.buffer.have_positions {
+            flags |= SerializeFlags::NO_POSITIONS;
+        }
+
+        let pos = &self.buffer.pos[..self.buffer.len];
+        GlyphBuffer::serialize_text(self.face, flags, self.glyph_infos(), pos).unwrap_or_default()
+    }
+}
+
 impl core::fmt::Debug for GlyphBuffer {
     fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         fmt.debug_struct("GlyphBuffer")
@@ -173,6 +173,40 @@ impl core::str::FromStr for Direction {
 }
 
 
+/// The orientation of a character in vertical text.
+///
+/// Defined by [UAX #50](https://www.unicode.org/reports/tr50/).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum VerticalOrientation {
+    /// Displayed upright, the same way as in horizontal text. `U`
+    Upright,
+    /// Displayed sideways, rotated 90 degrees clockwise. `R`
+    Rotated,
+    /// Displayed upright using a vertical glyph variant, like small kana
+    /// and ideographic punctuation. Upright when there is none. `Tu`
+    TransformedUpright,
+    /// Displayed upright using a vertical glyph variant, like brackets.
+    /// Rotated when there is none. `Tr`
+    TransformedRotated,
+}
+
+impl VerticalOrientation {
+    /// Checks that a character without a vertical glyph variant
+    /// is displayed upright.
+    #[inline]
+    pub fn is_upright(self) -> bool {
+        matches!(self, VerticalOrientation::Upright | VerticalOrientation::TransformedUpright)
+    }
+}
+
+/// Returns the Unicode vertical orientation of a character.
+///
+/// Unlisted characters, like Latin letters, are [`VerticalOrientation::Rotated`].
+pub fn vertical_orientation(c: char) -> VerticalOrientation {
+    crate::unicode::CharExt::vertical_orientation(c)
+}
+
+
 /// A script language.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Language(String);
@@ -461,10 +461,21 @@ fn arabic_joining(buffer: &mut Buffer) {
         }
 
         let entry = &STATE_TABLE[state][this_type as usize];
-        if entry.0 != action::NONE && prev.is_some() {
-            if let Some(prev) = prev {
+        match prev {
+            Some(prev) if entry.0 != action::NONE => {
                 buffer.info[prev].set_arabic_shaping_action(entry.0);
-                buffer.unsafe_to_break(prev, i + 1);
+                buffer.safe_to_insert_tatweel(prev, i + 1);
+            }
+            Some(prev) => {
+                // States that have a possible prev_action.
+                if this_type as u8 >= JoiningType::R as u8 || (2..=5).contains(&state) {
+                    buffer.unsafe_to_concat(prev, i + 1);
+                }
+            }
+            None => {
+                if this_type as u8 >= JoiningType::R as u8 {
+                    buffer.unsafe_to_concat_from_outbuffer(0, i + 1);
+                }
             }
         }
 
@@ -482,9 +493,13 @@ fn arabic_joining(buffer: &mut Buffer) {
         }
 
         let entry = &STATE_TABLE[state][this_type as usize];
-        if entry.0 != action::NONE && prev.is_some() {
-            if let Some(prev) = prev {
+        if let Some(prev) = prev {
+            if entry.0 != action::NONE {
                 buffer.info[prev].set_arabic_shaping_action(entry.0);
+                buffer.safe_to_insert_tatweel(prev, buffer.len);
+            } else if (2..=5).contains(&state) {
+                // States that have a possible prev_action.
+                buffer.unsafe_to_concat(prev, buffer.len);
             }
         }
 
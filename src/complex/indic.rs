@@ -685,6 +685,10 @@ fn setup_syllables(_: &ShapePlan, _: &Face, buffer: &mut Buffer) {
 }
 
 fn initial_reordering(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
+    if !buffer.message(face, format_args!("start reordering indic initial")) {
+        return;
+    }
+
     let indic_plan = plan.data::<IndicShapePlan>();
 
     update_consonant_positions(plan, indic_plan, face, buffer);
@@ -697,6 +701,8 @@ fn initial_reordering(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         start = end;
         end = buffer.next_syllable(start);
     }
+
+    buffer.message(face, format_args!("end reordering indic initial"));
 }
 
 fn update_consonant_positions(
@@ -1384,6 +1390,10 @@ fn final_reordering(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         return;
     }
 
+    if !buffer.message(face, format_args!("start reordering indic final")) {
+        return;
+    }
+
     let indic_plan = plan.data::<IndicShapePlan>();
 
     let mut virama_glyph = None;
@@ -1400,6 +1410,8 @@ fn final_reordering(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         start = end;
         end = buffer.next_syllable(start);
     }
+
+    buffer.message(face, format_args!("end reordering indic final"));
 }
 
 fn final_reordering_impl(
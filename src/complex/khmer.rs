@@ -147,6 +147,10 @@ fn setup_syllables(_: &ShapePlan, _: &Face, buffer: &mut Buffer) {
 }
 
 fn reorder(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
+    if !buffer.message(face, format_args!("start reordering khmer")) {
+        return;
+    }
+
     insert_dotted_circles(face, buffer);
 
     let khmer_plan = plan.data::<KhmerShapePlan>();
@@ -158,6 +162,8 @@ fn reorder(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         start = end;
         end = buffer.next_syllable(start);
     }
+
+    buffer.message(face, format_args!("end reordering khmer"));
 }
 
 fn insert_dotted_circles(face: &Face, buffer: &mut Buffer) {
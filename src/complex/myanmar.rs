@@ -222,6 +222,10 @@ fn setup_syllables(_: &ShapePlan, _: &Face, buffer: &mut Buffer) {
 }
 
 fn reorder(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
+    if !buffer.message(face, format_args!("start reordering myanmar")) {
+        return;
+    }
+
     insert_dotted_circles(face, buffer);
 
     let mut start = 0;
@@ -231,6 +235,8 @@ fn reorder(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         start = end;
         end = buffer.next_syllable(start);
     }
+
+    buffer.message(face, format_args!("end reordering myanmar"));
 }
 
 fn insert_dotted_circles(face: &Face, buffer: &mut Buffer) {
@@ -319,6 +319,10 @@ fn record_rphf(plan: &ShapePlan, _: &Face, buffer: &mut Buffer) {
 }
 
 fn reorder(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
+    if !buffer.message(face, format_args!("start reordering USE")) {
+        return;
+    }
+
     insert_dotted_circles(face, buffer);
 
     let mut start = 0;
@@ -328,6 +332,8 @@ fn reorder(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         start = end;
         end = buffer.next_syllable(start);
     }
+
+    buffer.message(face, format_args!("end reordering USE"));
 }
 
 fn insert_dotted_circles(face: &Face, buffer: &mut Buffer) {
@@ -1,10 +1,14 @@
-use ttf_parser::GlyphId;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use ttf_parser::{FromData, GlyphId, Tag};
 use ttf_parser::gdef::GlyphClass;
 use ttf_parser::opentype_layout::LayoutTable;
 
-use crate::Variation;
-use crate::ot::{TableIndex, PositioningTable, SubstitutionTable};
+use crate::{Direction, Language, Script, Variation};
+use crate::ot::{Layout, LookupGlyphs, LookupSet, TableIndex, PositioningTable, SubstitutionTable};
 use crate::buffer::GlyphPropsFlags;
+use crate::glyph_set::GlyphSet;
 
 
 // https://docs.microsoft.com/en-us/typography/opentype/spec/cmap#windows-platform-platform-id--3
@@ -27,11 +31,23 @@ const UNICODE_FULL_ENCODING: u16 = 6;
 pub struct Face<'a> {
     pub(crate) ttfp_face: ttf_parser::Face<'a>,
     pub(crate) units_per_em: u16,
+    x_scale: i32,
+    y_scale: i32,
+    x_mult: i64,
+    y_mult: i64,
+    embolden: (f32, f32),
+    embolden_in_place: bool,
+    slant: f32,
+    // Derived from the scale and the synthetic style.
+    pub(crate) x_strength: i32,
+    pub(crate) y_strength: i32,
+    pub(crate) slant_xy: f32,
     pixels_per_em: Option<(u16, u16)>,
     pub(crate) points_per_em: Option<f32>,
     prefered_cmap_encoding_subtable: Option<u16>,
     pub(crate) gsub: Option<SubstitutionTable<'a>>,
     pub(crate) gpos: Option<PositioningTable<'a>>,
+    font_funcs: Option<&'a (dyn FontFuncs + Sync)>,
 }
 
 impl<'a> AsRef<ttf_parser::Face<'a>> for Face<'a> {
@@ -77,13 +93,27 @@ impl<'a> Face<'a> {
     ///
     /// Data will be referenced, not owned.
     pub fn from_face(face: ttf_parser::Face<'a>) -> Self {
+        let units_per_em = face.units_per_em();
         Face {
-            units_per_em: face.units_per_em(),
+            units_per_em,
+            x_scale: i32::from(units_per_em),
+            y_scale: i32::from(units_per_em),
+            x_mult: 1 << 16,
+            y_mult: 1 << 16,
+            embolden: (0.0, 0.0),
+            embolden_in_place: false,
+            slant: 0.0,
+            x_strength: 0,
+            y_strength: 0,
+            slant_xy: 0.0,
             pixels_per_em: None,
             points_per_em: None,
             prefered_cmap_encoding_subtable: find_best_cmap_subtable(&face),
             gsub: face.tables().gsub.map(SubstitutionTable::new),
-            gpos: face.tables().gpos.map(PositioningTable::new),
+            gpos: face.tables().gpos.map(|table| {
+                PositioningTable::new(table, face.raw_face().table(Tag::from_bytes(b"GPOS")))
+            }),
+            font_funcs: None,
             ttfp_face: face,
         }
     }
@@ -95,6 +125,104 @@ impl<'a> Face<'a> {
         self.units_per_em as i32
     }
 
+    /// Sets the scale, i.e. the size of EM in output units.
+    ///
+    /// All glyph positions produced by shaping, including the ones from GPOS,
+    /// `kern`, `kerx`, `trak` and fallback positioning, are scaled accordingly.
+    /// Device table deltas are applied at [`set_pixels_per_em`](Self::set_pixels_per_em)
+    /// and scaled to the same units.
+    ///
+    /// Custom [`FontFuncs`] have to return values in these units as well.
+    ///
+    /// Units per EM by default, i.e. no scaling.
+    #[inline]
+    pub fn set_scale(&mut self, x_scale: i32, y_scale: i32) {
+        let upem = i64::from(self.units_per_em.max(1));
+        self.x_scale = x_scale;
+        self.y_scale = y_scale;
+        self.x_mult = (i64::from(x_scale) << 16) / upem;
+        self.y_mult = (i64::from(y_scale) << 16) / upem;
+        self.update_synthetic();
+    }
+
+    /// Returns the scale.
+    #[inline]
+    pub fn scale(&self) -> (i32, i32) {
+        (self.x_scale, self.y_scale)
+    }
+
+    /// Sets synthetic bold.
+    ///
+    /// `x_embolden` and `y_embolden` are the emboldening strengths in fractions
+    /// of EM, e.g. `0.02`. Glyph extents grow by the strength. Unless `in_place` is set,
+    /// non-zero advances grow as well, so emboldened glyphs do not overlap.
+    ///
+    /// Shaping only accounts for the strength in positions.
+    /// Glyph outlines have to be emboldened by the caller.
+    ///
+    /// Zero strength, i.e. no emboldening, by default.
+    pub fn set_synthetic_bold(&mut self, x_embolden: f32, y_embolden: f32, in_place: bool) {
+        self.embolden = (x_embolden, y_embolden);
+        self.embolden_in_place = in_place;
+        self.update_synthetic();
+    }
+
+    /// Returns synthetic bold strengths and whether emboldening is in place.
+    #[inline]
+    pub fn synthetic_bold(&self) -> (f32, f32, bool) {
+        (self.embolden.0, self.embolden.1, self.embolden_in_place)
+    }
+
+    /// Sets synthetic slant.
+    ///
+    /// `slant` is the horizontal shift per unit of height,
+    /// e.g. `0.2` for a slant of about 11 degrees. Glyph extents and offsets
+    /// of raised or lowered glyphs, like marks, are adjusted accordingly.
+    ///
+    /// Glyph outlines have to be slanted by the caller.
+    ///
+    /// `0.0` by default.
+    pub fn set_synthetic_slant(&mut self, slant: f32) {
+        self.slant = slant;
+        self.update_synthetic();
+    }
+
+    /// Returns synthetic slant.
+    #[inline]
+    pub fn synthetic_slant(&self) -> f32 {
+        self.slant
+    }
+
+    fn update_synthetic(&mut self) {
+        self.x_strength = crate::round(self.x_scale as f32 * self.embolden.0) as i32;
+        self.y_strength = crate::round(self.y_scale as f32 * self.embolden.1) as i32;
+        self.slant_xy = if self.y_scale != 0 {
+            self.slant * self.x_scale as f32 / self.y_scale as f32
+        } else {
+            0.0
+        };
+    }
+
+    #[inline]
+    pub(crate) fn em_scale_x(&self, v: i32) -> i32 {
+        em_mult(v, self.x_mult)
+    }
+
+    #[inline]
+    pub(crate) fn em_scale_y(&self, v: i32) -> i32 {
+        em_mult(v, self.y_mult)
+    }
+
+    #[inline]
+    pub(crate) fn em_fscale_x(&self, v: f32) -> f32 {
+        v * self.x_scale as f32 / f32::from(self.units_per_em)
+    }
+
+    #[inline]
+    pub(crate) fn em_fscale_y(&self, v: f32) -> f32 {
+        v * self.y_scale as f32 / f32::from(self.units_per_em)
+    }
+
     #[inline]
     pub(crate) fn pixels_per_em(&self) -> Option<(u16, u16)> {
         self.pixels_per_em
@@ -127,11 +255,160 @@ impl<'a> Face<'a> {
         }
     }
 
+    /// Sets custom font functions.
+    ///
+    /// When set, glyph lookup and glyph metrics are queried from `funcs`
+    /// instead of the font tables during shaping.
+    ///
+    /// `None` by default.
+    #[inline]
+    pub fn set_font_funcs(&mut self, funcs: Option<&'a (dyn FontFuncs + Sync)>) {
+        self.font_funcs = funcs;
+    }
+
+    #[inline]
+    pub(crate) fn has_font_funcs(&self) -> bool {
+        self.font_funcs.is_some()
+    }
+
     pub(crate) fn has_glyph(&self, c: u32) -> bool {
         self.glyph_index(c).is_some()
     }
 
     pub(crate) fn glyph_index(&self, c: u32) -> Option<GlyphId> {
+        let c = char::try_from(c).ok()?;
+        match self.font_funcs {
+            Some(funcs) => funcs.nominal_glyph(self, c),
+            None => DefaultFontFuncs.nominal_glyph(self, c),
+        }
+    }
+
+    pub(crate) fn glyph_variation_index(&self, c: char, variation_selector: char) -> Option<GlyphId> {
+        match self.font_funcs {
+            Some(funcs) => funcs.variation_glyph(self, c, variation_selector),
+            None => DefaultFontFuncs.variation_glyph(self, c, variation_selector),
+        }
+    }
+
+    /// Returns glyph's horizontal advance, as used by shaping.
+    ///
+    /// Uses `hmtx` and `HVAR`. For variable fonts without `HVAR`,
+    /// the advance is derived from the glyph's bounding box.
+    /// Synthetic bold and custom [`FontFuncs`] are taken into account.
+    ///
+    /// The value is in the units set by [`set_scale`](Self::set_scale).
+    pub fn glyph_h_advance(&self, glyph: GlyphId) -> i32 {
+        let advance = match self.font_funcs {
+            Some(funcs) => funcs.glyph_h_advance(self, glyph),
+            None => DefaultFontFuncs.glyph_h_advance(self, glyph),
+        };
+
+        if advance != 0 && !self.embolden_in_place {
+            advance + if self.x_scale >= 0 { self.x_strength } else { -self.x_strength }
+        } else {
+            advance
+        }
+    }
+
+    /// Returns glyph's vertical advance, as used by shaping.
+    ///
+    /// Uses `vmtx` and `VVAR` the same way [`glyph_h_advance`](Self::glyph_h_advance)
+    /// uses `hmtx` and `HVAR`. Falls back to one EM when there is no `vmtx`.
+    ///
+    /// The value is in the units set by [`set_scale`](Self::set_scale).
+    /// The advance is negative for a positive scale, since the Y axis goes up.
+    pub fn glyph_v_advance(&self, glyph: GlyphId) -> i32 {
+        let advance = match self.font_funcs {
+            Some(funcs) => funcs.glyph_v_advance(self, glyph),
+            None => DefaultFontFuncs.glyph_v_advance(self, glyph),
+        };
+
+        // Vertical advances are negative.
+        if advance != 0 && !self.embolden_in_place {
+            advance + if self.y_scale >= 0 { -self.y_strength } else { self.y_strength }
+        } else {
+            advance
+        }
+    }
+
+    /// Returns glyph's horizontal origin as `(x, y)`.
+    ///
+    /// Always `(0, 0)`, unless overridden by custom [`FontFuncs`].
+    pub fn glyph_h_origin(&self, glyph: GlyphId) -> (i32, i32) {
+        match self.font_funcs {
+            Some(funcs) => funcs.glyph_h_origin(self, glyph),
+            None => DefaultFontFuncs.glyph_h_origin(self, glyph),
+        }
+    }
+
+    /// Returns glyph's vertical origin as `(x, y)`.
+    ///
+    /// X is half of the horizontal advance. Y comes from `VORG`,
+    /// or from the glyph's top side bearing and its extents.
+    pub fn glyph_v_origin(&self, glyph: GlyphId) -> (i32, i32) {
+        match self.font_funcs {
+            Some(funcs) => funcs.glyph_v_origin(self, glyph),
+            None => DefaultFontFuncs.glyph_v_origin(self, glyph),
+        }
+    }
+
+    /// Returns glyph's extents.
+    ///
+    /// PNG bitmaps matching [`set_pixels_per_em`](Self::set_pixels_per_em) are preferred
+    /// over the outline bounding box. Synthetic bold and slant are taken into account.
+    ///
+    /// Returns `None` when the glyph has neither.
+    pub fn glyph_extents(&self, glyph: GlyphId) -> Option<GlyphExtents> {
+        let mut extents = match self.font_funcs {
+            Some(funcs) => funcs.glyph_extents(self, glyph),
+            None => DefaultFontFuncs.glyph_extents(self, glyph),
+        }?;
+
+        self.synthetic_glyph_extents(&mut extents);
+        Some(extents)
+    }
+
+    /// Returns the coordinates of a glyph outline point as `(x, y)`.
+    ///
+    /// Used by format 2 `GPOS` anchors when [`set_pixels_per_em`](Self::set_pixels_per_em)
+    /// is set. Always `None`, unless overridden by custom [`FontFuncs`].
+    pub fn glyph_contour_point(&self, glyph: GlyphId, point_index: u16) -> Option<(i32, i32)> {
+        match self.font_funcs {
+            Some(funcs) => funcs.glyph_contour_point(self, glyph, point_index),
+            None => DefaultFontFuncs.glyph_contour_point(self, glyph, point_index),
+        }
+    }
+
+    fn synthetic_glyph_extents(&self, extents: &mut GlyphExtents) {
+        if self.slant_xy != 0.0 {
+            let x1 = extents.x_bearing as f32;
+            let y1 = extents.y_bearing as f32;
+            let x2 = (extents.x_bearing + extents.width) as f32;
+            let y2 = (extents.y_bearing + extents.height) as f32;
+
+            let shift1 = y1 * self.slant_xy;
+            let shift2 = y2 * self.slant_xy;
+            let x1 = x1 + crate::round(shift1.min(shift2));
+            let x2 = x2 + crate::round(shift1.max(shift2));
+
+            extents.x_bearing = x1 as i32;
+            extents.width = x2 as i32 - extents.x_bearing;
+        }
+
+        if self.x_strength != 0 || self.y_strength != 0 {
+            let y_shift = if self.y_scale >= 0 { self.y_strength } else { -self.y_strength };
+            extents.y_bearing += y_shift;
+            extents.height -= y_shift;
+
+            let x_shift = if self.x_scale >= 0 { self.x_strength } else { -self.x_strength };
+            if self.embolden_in_place {
+                extents.x_bearing -= x_shift / 2;
+            }
+            extents.width += x_shift;
+        }
+    }
+
+    fn table_glyph_index(&self, c: u32) -> Option<GlyphId> {
         let subtable_idx = self.prefered_cmap_encoding_subtable?;
         let subtable = self.tables().cmap?.subtables.get(subtable_idx)?;
         match subtable.glyph_index(c) {
@@ -148,7 +425,7 @@ impl<'a> Face<'a> {
                         // Windows seems to do, and that's hinted about at:
                         // https://docs.microsoft.com/en-us/typography/opentype/spec/recom
                         // under "Non-Standard (Symbol) Fonts".
-                        return self.glyph_index(0xF000 + c);
+                        return self.table_glyph_index(0xF000 + c);
                     }
                 }
 
@@ -157,15 +434,16 @@ impl<'a> Face<'a> {
         }
     }
 
-    pub(crate) fn glyph_h_advance(&self, glyph: GlyphId) -> i32 {
-        self.glyph_advance(glyph, false) as i32
-    }
-
-    pub(crate) fn glyph_v_advance(&self, glyph: GlyphId) -> i32 {
-        -(self.glyph_advance(glyph, true) as i32)
+    fn table_glyph_advance(&self, glyph: GlyphId, is_vertical: bool) -> i32 {
+        let advance = self.table_glyph_advance_unscaled(glyph, is_vertical) as i32;
+        if is_vertical {
+            self.em_scale_y(advance)
+        } else {
+            self.em_scale_x(advance)
+        }
     }
 
-    fn glyph_advance(&self, glyph: GlyphId, is_vertical: bool) -> u32 {
+    fn table_glyph_advance_unscaled(&self, glyph: GlyphId, is_vertical: bool) -> u32 {
         let face = &self.ttfp_face;
         if face.is_variable() &&
            face.has_non_default_variation_coordinates() &&
@@ -193,19 +471,15 @@ impl<'a> Face<'a> {
         }
     }
 
-    pub(crate) fn glyph_h_origin(&self, glyph: GlyphId) -> i32 {
-        self.glyph_h_advance(glyph) / 2
-    }
-
-    pub(crate) fn glyph_v_origin(&self, glyph: GlyphId) -> i32 {
+    fn table_glyph_v_origin_y(&self, glyph: GlyphId) -> i32 {
         match self.ttfp_face.glyph_y_origin(glyph) {
-            Some(y) => i32::from(y),
+            Some(y) => self.em_scale_y(i32::from(y)),
             None => self.glyph_extents(glyph).map_or(0, |ext| ext.y_bearing)
-                + self.glyph_side_bearing(glyph, true)
+                + self.em_scale_y(self.glyph_side_bearing(glyph, true))
         }
     }
 
-    pub(crate) fn glyph_side_bearing(&self, glyph: GlyphId, is_vertical: bool) -> i32 {
+    fn glyph_side_bearing(&self, glyph: GlyphId, is_vertical: bool) -> i32 {
         let face = &self.ttfp_face;
         if  face.is_variable() &&
             face.tables().hvar.is_none() &&
@@ -224,7 +498,7 @@ impl<'a> Face<'a> {
         }
     }
 
-    pub(crate) fn glyph_extents(&self, glyph: GlyphId) -> Option<GlyphExtents> {
+    fn table_glyph_extents(&self, glyph: GlyphId) -> Option<GlyphExtents> {
         let pixels_per_em = match self.pixels_per_em {
             Some(ppem) => ppem.0,
             None => core::u16::MAX,
@@ -233,26 +507,246 @@ impl<'a> Face<'a> {
         if let Some(img) = self.ttfp_face.glyph_raster_image(glyph, pixels_per_em) {
             // HarfBuzz also supports only PNG.
             if img.format == ttf_parser::RasterImageFormat::PNG {
-                let scale = self.units_per_em as f32 / img.pixels_per_em as f32;
+                let x_scale = self.x_scale as f32 / img.pixels_per_em as f32;
+                let y_scale = self.y_scale as f32 / img.pixels_per_em as f32;
                 return Some(GlyphExtents {
-                    x_bearing: crate::round(f32::from(img.x) * scale) as i32,
-                    y_bearing: crate::round((f32::from(img.y) + f32::from(img.height)) * scale) as i32,
-                    width: crate::round(f32::from(img.width) * scale) as i32,
-                    height: crate::round(-f32::from(img.height) * scale) as i32,
+                    x_bearing: crate::round(f32::from(img.x) * x_scale) as i32,
+                    y_bearing: crate::round((f32::from(img.y) + f32::from(img.height)) * y_scale) as i32,
+                    width: crate::round(f32::from(img.width) * x_scale) as i32,
+                    height: crate::round(-f32::from(img.height) * y_scale) as i32,
                 });
             }
         }
 
         let bbox = self.ttfp_face.glyph_bounding_box(glyph)?;
+        let x_bearing = self.em_scale_x(i32::from(bbox.x_min));
+        let y_bearing = self.em_scale_y(i32::from(bbox.y_max));
         Some(GlyphExtents {
-            x_bearing: i32::from(bbox.x_min),
-            y_bearing: i32::from(bbox.y_max),
-            width: i32::from(bbox.width()),
-            height: i32::from(bbox.y_min - bbox.y_max),
+            x_bearing,
+            y_bearing,
+            width: self.em_scale_x(i32::from(bbox.x_max)) - x_bearing,
+            height: self.em_scale_y(i32::from(bbox.y_min)) - y_bearing,
+        })
+    }
+
+    /// Returns font-wide extents for the given direction.
+    ///
+    /// Horizontal extents come from `OS/2` typographic metrics when
+    /// `USE_TYPO_METRICS` is set and from `hhea` otherwise.
+    /// Vertical extents come from `vhea`. `MVAR` deltas are applied
+    /// for the current [variations](Self::set_variations).
+    ///
+    /// A zero `hhea` ascender and descender are used as is, like in harfbuzz.
+    /// When the font has no `vhea`, the vertical ascender is 50% of EM
+    /// and the descender is the rest of it.
+    ///
+    /// The values are in the units set by [`set_scale`](Self::set_scale).
+    pub fn font_extents(&self, direction: Direction) -> FontExtents {
+        match direction {
+            Direction::TopToBottom | Direction::BottomToTop => {
+                self.table_v_extents().unwrap_or_else(|| {
+                    let ascender = self.x_scale / 2;
+                    FontExtents { ascender, descender: ascender - self.x_scale, line_gap: 0 }
+                })
+            }
+            _ => {
+                self.table_h_extents().unwrap_or_else(|| {
+                    let ascender = crate::round(self.y_scale as f32 * 0.8) as i32;
+                    FontExtents { ascender, descender: ascender - self.y_scale, line_gap: 0 }
+                })
+            }
+        }
+    }
+
+    fn table_h_extents(&self) -> Option<FontExtents> {
+        Some(FontExtents {
+            ascender: self.metric(MetricTag::HorizontalAscender)?,
+            descender: self.metric(MetricTag::HorizontalDescender)?,
+            line_gap: self.metric(MetricTag::HorizontalLineGap)?,
+        })
+    }
+
+    fn table_v_extents(&self) -> Option<FontExtents> {
+        Some(FontExtents {
+            ascender: self.metric(MetricTag::VerticalAscender)?,
+            descender: self.metric(MetricTag::VerticalDescender)?,
+            line_gap: self.metric(MetricTag::VerticalLineGap)?,
         })
     }
 
-    pub(crate) fn glyph_name(&self, glyph: GlyphId) -> Option<&str> {
+    /// Returns a font-wide metric.
+    ///
+    /// Values come from `OS/2`, `hhea`, `vhea` and `post`, the same way
+    /// as in [`font_extents`](Self::font_extents), and are adjusted by `MVAR` deltas
+    /// for the current [variations](Self::set_variations).
+    /// `HorizontalAscender` and `VerticalAscender` are always positive,
+    /// `HorizontalDescender` and `VerticalDescender` are always negative.
+    /// `HorizontalClippingDescent` is positive below the baseline, like `usWinDescent`.
+    ///
+    /// The value is in the units set by [`set_scale`](Self::set_scale).
+    ///
+    /// Returns `None` when the font doesn't have the required table.
+    pub fn metric(&self, metric: MetricTag) -> Option<i32> {
+        use MetricTag as M;
+
+        let tables = self.tables();
+        let os2 = tables.os2;
+        let caret = |table: &[u8; 4], offset: usize| {
+            let data = self.raw_face().table(Tag::from_bytes(table))?;
+            data.get(offset..offset + 2).and_then(i16::parse)
+        };
+
+        // Whether the metric is scaled along the X axis.
+        let (value, is_x) = match metric {
+            M::HorizontalAscender => (self.h_line_metrics().0, false),
+            M::HorizontalDescender => (self.h_line_metrics().1, false),
+            M::HorizontalLineGap => (self.h_line_metrics().2, false),
+            M::HorizontalClippingAscent => (os2?.windows_ascender(), false),
+            M::HorizontalClippingDescent => (-os2?.windows_descender(), false),
+            M::VerticalAscender => (tables.vhea?.ascender, true),
+            M::VerticalDescender => (tables.vhea?.descender, true),
+            M::VerticalLineGap => (tables.vhea?.line_gap, true),
+            M::HorizontalCaretRise => (caret(b"hhea", 18)?, false),
+            M::HorizontalCaretRun => (caret(b"hhea", 20)?, true),
+            M::HorizontalCaretOffset => (caret(b"hhea", 22)?, true),
+            M::VerticalCaretRise => (caret(b"vhea", 18)?, true),
+            M::VerticalCaretRun => (caret(b"vhea", 20)?, false),
+            M::VerticalCaretOffset => (caret(b"vhea", 22)?, false),
+            M::XHeight => (os2?.x_height()?, false),
+            M::CapHeight => (os2?.capital_height()?, false),
+            M::SubscriptEmXSize => (os2?.subscript_metrics().x_size, true),
+            M::SubscriptEmYSize => (os2?.subscript_metrics().y_size, false),
+            M::SubscriptEmXOffset => (os2?.subscript_metrics().x_offset, true),
+            M::SubscriptEmYOffset => (os2?.subscript_metrics().y_offset, false),
+            M::SuperscriptEmXSize => (os2?.superscript_metrics().x_size, true),
+            M::SuperscriptEmYSize => (os2?.superscript_metrics().y_size, false),
+            M::SuperscriptEmXOffset => (os2?.superscript_metrics().x_offset, true),
+            M::SuperscriptEmYOffset => (os2?.superscript_metrics().y_offset, false),
+            M::StrikeoutSize => (os2?.strikeout_metrics().thickness, false),
+            M::StrikeoutOffset => (os2?.strikeout_metrics().position, false),
+            M::UnderlineSize => (tables.post?.underline_metrics.thickness, false),
+            M::UnderlineOffset => (tables.post?.underline_metrics.position, false),
+        };
+
+        let mut value = f32::from(value) + self.metric_variation(metric.tag());
+        match metric {
+            M::HorizontalAscender | M::VerticalAscender => value = value.abs(),
+            M::HorizontalDescender | M::VerticalDescender => value = -value.abs(),
+            _ => {}
+        }
+
+        let value = if is_x { self.em_fscale_x(value) } else { self.em_fscale_y(value) };
+        Some(crate::round(value) as i32)
+    }
+
+    fn h_line_metrics(&self) -> (i16, i16, i16) {
+        match self.tables().os2 {
+            Some(os2) if os2.use_typographic_metrics() => (
+                os2.typographic_ascender(),
+                os2.typographic_descender(),
+                os2.typographic_line_gap(),
+            ),
+            _ => {
+                let hhea = &self.tables().hhea;
+                (hhea.ascender, hhea.descender, hhea.line_gap)
+            }
+        }
+    }
+
+    /// Returns the `MVAR` delta of a metric for the current variation coordinates.
+    pub(crate) fn metric_variation(&self, tag: Tag) -> f32 {
+        self.tables().mvar
+            .and_then(|mvar| mvar.metric_offset(tag, self.variation_coordinates()))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns OpenType layout information, i.e. scripts, language systems
+    /// and features of `GSUB` and `GPOS`.
+    #[inline]
+    pub fn layout(&self) -> Layout<'_> {
+        Layout::new(self)
+    }
+
+    /// Returns alternates of a glyph for a feature, like in `aalt`, `salt` or `swsh`.
+    ///
+    /// The feature lookups are resolved for the script and language
+    /// the same way as during shaping, including feature variations.
+    /// Single and alternate substitution lookups are followed.
+    /// Alternates are returned in lookup order, without duplicates. Alternates of
+    /// an alternate substitution keep their order, i.e. the order of feature values.
+    ///
+    /// Only `GSUB` has alternates; `GPOS` always returns an empty list.
+    pub fn glyph_alternates(
+        &self,
+        table: TableIndex,
+        script: Option<Script>,
+        language: Option<&Language>,
+        feature: Tag,
+        glyph: GlyphId,
+    ) -> Vec<GlyphId> {
+        match table {
+            TableIndex::GSUB => crate::ot::glyph_alternates(self, script, language, feature, glyph),
+            TableIndex::GPOS => Vec::new(),
+        }
+    }
+
+    /// Checks whether a `GSUB` feature would substitute a glyph sequence.
+    ///
+    /// The feature lookups are resolved for the script and language
+    /// the same way as during shaping. When `zero_context` is set,
+    /// lookups that require context around the glyphs don't match.
+    ///
+    /// Useful to find out which features affect the given text.
+    pub fn would_substitute(
+        &self,
+        script: Option<Script>,
+        language: Option<&Language>,
+        feature: Tag,
+        glyphs: &[GlyphId],
+        zero_context: bool,
+    ) -> bool {
+        crate::ot::would_substitute(self, script, language, feature, glyphs, zero_context)
+    }
+
+    /// Collects lookups of features for scripts and language systems.
+    ///
+    /// `None` means all scripts, all language systems (including the default ones)
+    /// or all features respectively. The required feature of a language system
+    /// is included only when all features are requested. Feature substitutes
+    /// for the current [variations](Self::set_variations) are used.
+    pub fn collect_lookups(
+        &self,
+        table: TableIndex,
+        scripts: Option<&[Tag]>,
+        languages: Option<&[Tag]>,
+        features: Option<&[Tag]>,
+    ) -> LookupSet {
+        crate::ot::collect_lookups(self, table, scripts, languages, features)
+    }
+
+    /// Collects glyphs that a lookup can match and produce.
+    ///
+    /// Glyphs matched by class 0 of a class definition are not included,
+    /// since it implicitly contains all unlisted glyphs.
+    pub fn collect_glyphs(&self, table: TableIndex, lookup: u16) -> LookupGlyphs {
+        crate::ot::collect_glyphs(self, table, lookup)
+    }
+
+    /// Computes the `GSUB` closure of a glyph set.
+    ///
+    /// Returns all glyphs that substitutions of the given features can produce
+    /// from `glyphs`, including `glyphs` themselves. Lookups of all scripts and
+    /// languages are used. When `features` is `None`, all features are used.
+    ///
+    /// Contextual lookups are followed when all glyphs of their context are in the set,
+    /// up to the same nesting level as during shaping.
+    pub fn substitute_closure(&self, features: Option<&[Tag]>, glyphs: &GlyphSet) -> GlyphSet {
+        crate::ot::substitute_closure(self, features, glyphs)
+    }
+
+    /// Returns glyph's name from `post` or `CFF`.
+    #[inline]
+    pub fn glyph_name(&self, glyph: GlyphId) -> Option<&str> {
         self.ttfp_face.glyph_name(glyph)
     }
 
@@ -283,16 +777,264 @@ impl<'a> Face<'a> {
     pub(crate) fn layout_tables(&self) -> impl Iterator<Item = (TableIndex, &LayoutTable<'a>)> + '_ {
         TableIndex::iter().filter_map(move |idx| self.layout_table(idx).map(|table| (idx, table)))
     }
+
+    pub(crate) fn key(&self) -> FaceKey {
+        let raw = self.ttfp_face.raw_face();
+        FaceKey {
+            data: (raw.data.as_ptr() as usize, raw.data.len()),
+            // Faces inside a collection share the same data, but not the same tables.
+            head: raw.table(Tag::from_bytes(b"head")).map_or(0, |data| data.as_ptr() as usize),
+            coords: self.ttfp_face.variation_coordinates().iter().map(|c| c.get()).collect(),
+        }
+    }
+}
+
+/// Identifies the font data and variation coordinates of a face.
+///
+/// Two faces have the same key when they reference the same data
+/// and have the same variation coordinates.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct FaceKey {
+    data: (usize, usize),
+    head: usize,
+    coords: Vec<i16>,
+}
+
+impl FaceKey {
+    /// Checks that the key belongs to the face, without allocating a new key.
+    pub(crate) fn matches(&self, face: &Face) -> bool {
+        let raw = face.ttfp_face.raw_face();
+        let head = raw.table(Tag::from_bytes(b"head")).map_or(0, |data| data.as_ptr() as usize);
+        let coords = face.ttfp_face.variation_coordinates();
+        self.data == (raw.data.as_ptr() as usize, raw.data.len())
+            && self.head == head
+            && self.coords.len() == coords.len()
+            && self.coords.iter().zip(coords).all(|(a, b)| *a == b.get())
+    }
 }
 
-#[derive(Clone, Copy, Default)]
+/// Glyph extents, in the units set by [`Face::set_scale`].
+///
+/// Follows the Y-up convention: `y_bearing` is the top of the glyph
+/// and `height` goes down from it, so it's negative for any non-empty outline.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 pub struct GlyphExtents {
+    /// Left side of the glyph from the origin.
     pub x_bearing: i32,
+    /// Top side of the glyph from the origin.
     pub y_bearing: i32,
+    /// Distance from the left to the right side.
     pub width: i32,
+    /// Distance from the top to the bottom side.
     pub height: i32,
 }
 
+/// Font-wide extents.
+///
+/// Follows the Y-up convention: `descender` is negative
+/// for a positive scale.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FontExtents {
+    /// Distance from the baseline to the top of the line.
+    pub ascender: i32,
+    /// Distance from the baseline to the bottom of the line.
+    pub descender: i32,
+    /// Suggested gap between lines.
+    pub line_gap: i32,
+}
+
+/// A font-wide metric.
+///
+/// Each metric corresponds to an `MVAR` value tag.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MetricTag {
+    /// Horizontal ascender. `hasc`
+    HorizontalAscender,
+    /// Horizontal descender. `hdsc`
+    HorizontalDescender,
+    /// Horizontal line gap. `hlgp`
+    HorizontalLineGap,
+    /// Horizontal clipping ascent, i.e. `OS/2.usWinAscent`. `hcla`
+    HorizontalClippingAscent,
+    /// Horizontal clipping descent, i.e. `OS/2.usWinDescent`.
+    /// Positive below the baseline. `hcld`
+    HorizontalClippingDescent,
+    /// Vertical ascender. `vasc`
+    VerticalAscender,
+    /// Vertical descender. `vdsc`
+    VerticalDescender,
+    /// Vertical line gap. `vlgp`
+    VerticalLineGap,
+    /// Horizontal caret rise. `hcrs`
+    HorizontalCaretRise,
+    /// Horizontal caret run. `hcrn`
+    HorizontalCaretRun,
+    /// Horizontal caret offset. `hcof`
+    HorizontalCaretOffset,
+    /// Vertical caret rise. `vcrs`
+    VerticalCaretRise,
+    /// Vertical caret run. `vcrn`
+    VerticalCaretRun,
+    /// Vertical caret offset. `vcof`
+    VerticalCaretOffset,
+    /// X height. `xhgt`
+    XHeight,
+    /// Cap height. `cpht`
+    CapHeight,
+    /// Subscript em x size. `sbxs`
+    SubscriptEmXSize,
+    /// Subscript em y size. `sbys`
+    SubscriptEmYSize,
+    /// Subscript em x offset. `sbxo`
+    SubscriptEmXOffset,
+    /// Subscript em y offset. `sbyo`
+    SubscriptEmYOffset,
+    /// Superscript em x size. `spxs`
+    SuperscriptEmXSize,
+    /// Superscript em y size. `spys`
+    SuperscriptEmYSize,
+    /// Superscript em x offset. `spxo`
+    SuperscriptEmXOffset,
+    /// Superscript em y offset. `spyo`
+    SuperscriptEmYOffset,
+    /// Strikeout size. `strs`
+    StrikeoutSize,
+    /// Strikeout offset. `stro`
+    StrikeoutOffset,
+    /// Underline size. `unds`
+    UnderlineSize,
+    /// Underline offset. `undo`
+    UnderlineOffset,
+}
+
+impl MetricTag {
+    /// Returns the `MVAR` value tag.
+    pub fn tag(self) -> Tag {
+        use MetricTag as M;
+        let tag = match self {
+            M::HorizontalAscender => b"hasc",
+            M::HorizontalDescender => b"hdsc",
+            M::HorizontalLineGap => b"hlgp",
+            M::HorizontalClippingAscent => b"hcla",
+            M::HorizontalClippingDescent => b"hcld",
+            M::VerticalAscender => b"vasc",
+            M::VerticalDescender => b"vdsc",
+            M::VerticalLineGap => b"vlgp",
+            M::HorizontalCaretRise => b"hcrs",
+            M::HorizontalCaretRun => b"hcrn",
+            M::HorizontalCaretOffset => b"hcof",
+            M::VerticalCaretRise => b"vcrs",
+            M::VerticalCaretRun => b"vcrn",
+            M::VerticalCaretOffset => b"vcof",
+            M::XHeight => b"xhgt",
+            M::CapHeight => b"cpht",
+            M::SubscriptEmXSize => b"sbxs",
+            M::SubscriptEmYSize => b"sbys",
+            M::SubscriptEmXOffset => b"sbxo",
+            M::SubscriptEmYOffset => b"sbyo",
+            M::SuperscriptEmXSize => b"spxs",
+            M::SuperscriptEmYSize => b"spys",
+            M::SuperscriptEmXOffset => b"spxo",
+            M::SuperscriptEmYOffset => b"spyo",
+            M::StrikeoutSize => b"strs",
+            M::StrikeoutOffset => b"stro",
+            M::UnderlineSize => b"unds",
+            M::UnderlineOffset => b"undo",
+        };
+
+        Tag::from_bytes(tag)
+    }
+}
+
+/// Font functions used during shaping.
+///
+/// Allows overriding glyph lookup and glyph metrics,
+/// for example to use hinted or pixel-snapped advances or a synthetic font.
+/// Every method has a default implementation that reads the font tables.
+/// The default implementations can be called explicitly via [`DefaultFontFuncs`].
+///
+/// All values are in the units set by [`Face::set_scale`].
+/// Synthetic bold and slant are applied on top of them.
+///
+/// `face` is the face the functions are set on. Calling its methods will use
+/// the custom functions as well, like the default vertical origin does
+/// for the horizontal advance and the extents.
+///
+/// Set with [`Face::set_font_funcs`].
+pub trait FontFuncs {
+    /// Returns a nominal glyph for a character.
+    fn nominal_glyph(&self, face: &Face, c: char) -> Option<GlyphId> {
+        face.table_glyph_index(u32::from(c))
+    }
+
+    /// Returns a glyph for a character followed by a variation selector.
+    ///
+    /// When `None` is returned, the character and the selector are mapped
+    /// separately and left for GSUB to handle.
+    fn variation_glyph(&self, face: &Face, c: char, variation_selector: char) -> Option<GlyphId> {
+        face.ttfp_face.glyph_variation_index(c, variation_selector)
+    }
+
+    /// Returns a horizontal glyph advance.
+    fn glyph_h_advance(&self, face: &Face, glyph: GlyphId) -> i32 {
+        face.table_glyph_advance(glyph, false)
+    }
+
+    /// Returns a vertical glyph advance.
+    ///
+    /// Negative for the top-to-bottom direction.
+    fn glyph_v_advance(&self, face: &Face, glyph: GlyphId) -> i32 {
+        -face.table_glyph_advance(glyph, true)
+    }
+
+    /// Returns the origin of a glyph in horizontal text, relative to the
+    /// origin used by the font outlines.
+    ///
+    /// `(0, 0)` by default.
+    fn glyph_h_origin(&self, face: &Face, glyph: GlyphId) -> (i32, i32) {
+        let _ = (face, glyph);
+        (0, 0)
+    }
+
+    /// Returns the origin of a glyph in vertical text, relative to the
+    /// origin used by the font outlines.
+    ///
+    /// By default, it's horizontally centered and placed on top of the glyph,
+    /// according to the `VORG` or `vmtx` tables.
+    fn glyph_v_origin(&self, face: &Face, glyph: GlyphId) -> (i32, i32) {
+        (face.glyph_h_advance(glyph) / 2, face.table_glyph_v_origin_y(glyph))
+    }
+
+    /// Returns glyph extents.
+    fn glyph_extents(&self, face: &Face, glyph: GlyphId) -> Option<GlyphExtents> {
+        face.table_glyph_extents(glyph)
+    }
+
+    /// Returns the coordinates of a glyph outline point.
+    ///
+    /// Format 2 `GPOS` anchors are placed on an outline point when pixels per EM are set,
+    /// so hinted outlines can be taken into account.
+    ///
+    /// `None` by default, in which case the anchor coordinates are used.
+    fn glyph_contour_point(&self, face: &Face, glyph: GlyphId, point_index: u16) -> Option<(i32, i32)> {
+        let _ = (face, glyph, point_index);
+        None
+    }
+}
+
+/// Font functions that read the font tables.
+///
+/// Can be used to call the default implementation from a [`FontFuncs`] override.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DefaultFontFuncs;
+
+impl FontFuncs for DefaultFontFuncs {}
+
+#[inline]
+fn em_mult(v: i32, mult: i64) -> i32 {
+    ((i64::from(v) * mult + 32768) >> 16) as i32
+}
+
 fn find_best_cmap_subtable(face: &ttf_parser::Face) -> Option<u16> {
     use ttf_parser::PlatformId;
 
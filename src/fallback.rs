@@ -306,7 +306,7 @@ fn position_mark(
         None => return,
     };
 
-    let y_gap = face.units_per_em as i32 / 16;
+    let y_gap = face.scale().1 / 16;
     pos.x_offset = 0;
     pos.y_offset = 0;
 
@@ -410,6 +410,10 @@ fn position_mark(
 
         _ => {}
     }
+
+    if face.slant_xy != 0.0 && pos.y_offset != 0 {
+        pos.x_offset += crate::round(face.slant_xy * pos.y_offset as f32) as i32;
+    }
 }
 
 pub fn adjust_spaces(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
@@ -429,20 +433,20 @@ pub fn adjust_spaces(_: &ShapePlan, face: &Face, buffer: &mut Buffer) {
             space::SPACE_EM_5 |
             space::SPACE_EM_6 |
             space::SPACE_EM_16 => {
-                let length = (face.units_per_em as i32 + (space_type as i32) / 2) / space_type as i32;
+                let (x_scale, y_scale) = face.scale();
                 if horizontal {
-                    pos.x_advance = length;
+                    pos.x_advance = (x_scale + (space_type as i32) / 2) / space_type as i32;
                 } else {
-                    pos.y_advance = -length;
+                    pos.y_advance = -(y_scale + (space_type as i32) / 2) / space_type as i32;
                 }
             }
 
             space::SPACE_4_EM_18 => {
-                let length = ((face.units_per_em as i64) * 4 / 18) as i32;
+                let (x_scale, y_scale) = face.scale();
                 if horizontal {
-                    pos.x_advance = length
+                    pos.x_advance = (i64::from(x_scale) * 4 / 18) as i32;
                 } else {
-                    pos.y_advance = -length;
+                    pos.y_advance = -(i64::from(y_scale) * 4 / 18) as i32;
                 }
             }
 
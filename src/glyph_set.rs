@@ -30,6 +30,26 @@ impl GlyphSet {
             }
         }).is_ok()
     }
+
+    /// Returns the number of glyphs in the set.
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(|range| usize::from(range.end().0 - range.start().0) + 1).sum()
+    }
+
+    /// Checks whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns an iterator over the glyphs in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = GlyphId> + '_ {
+        self.ranges.iter().flat_map(|range| (range.start().0..=range.end().0).map(GlyphId))
+    }
+
+    /// Creates a builder that already contains the glyphs of this set.
+    pub(crate) fn to_builder(&self) -> GlyphSetBuilder {
+        GlyphSetBuilder { ranges: self.ranges.clone() }
+    }
 }
 
 /// A builder for a [`GlyphSet`].
@@ -115,6 +135,18 @@ mod tests {
         assert!(!set.contains(GlyphId(6)));
     }
 
+    #[test]
+    fn test_len_and_iter() {
+        let mut builder = GlyphSet::builder();
+        builder.insert_range(GlyphId(3)..=GlyphId(5));
+        builder.insert(GlyphId(1));
+        builder.insert(GlyphId(4));
+        let set = builder.finish();
+        assert_eq!(set.len(), 4);
+        assert!(!set.is_empty());
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![GlyphId(1), GlyphId(3), GlyphId(4), GlyphId(5)]);
+    }
+
     #[test]
     fn test_merge_ranges() {
         let mut builder = GlyphSet::builder();
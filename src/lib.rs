@@ -36,12 +36,20 @@ pub use ttf_parser;
 pub use ttf_parser::Tag;
 
 pub use crate::buffer::{
-    GlyphPosition, GlyphInfo, BufferClusterLevel,
-    SerializeFlags, UnicodeBuffer, GlyphBuffer
+    GlyphPosition, GlyphInfo, BufferClusterLevel, BufferDiffFlags, BufferFlags, ClusterUnit,
+    SerializeFlags, SerializeFormat, UnicodeBuffer, GlyphBuffer
 };
-pub use crate::common::{Direction, Script, Language, Feature, Variation, script};
-pub use crate::face::Face;
-pub use crate::shape::shape;
+#[cfg(feature = "trace")]
+pub use crate::buffer::BufferView;
+pub use crate::common::{
+    vertical_orientation, Direction, Script, Language, Feature, Variation, VerticalOrientation,
+    script,
+};
+pub use crate::face::{DefaultFontFuncs, Face, FontExtents, FontFuncs, GlyphExtents, MetricTag};
+pub use crate::glyph_set::{GlyphSet, GlyphSetBuilder};
+pub use crate::ot::{FeatureNames, Layout, LookupGlyphs, LookupSet, TableIndex};
+pub use crate::plan::{PlanCache, ShapePlan, ShapePlanError};
+pub use crate::shape::{shape, shape_with_plan};
 
 type Mask = u32;
 
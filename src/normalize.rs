@@ -286,10 +286,10 @@ fn decompose_multi_char_cluster(ctx: &mut ShapeNormalizeContext, end: usize, sho
 
 fn handle_variation_selector_cluster(ctx: &mut ShapeNormalizeContext, end: usize, _: bool) {
     let face = ctx.face;
+    let not_found = ctx.buffer.not_found;
     let set_glyph = |info: &mut GlyphInfo| {
-        if let Some(glyph_id) = face.glyph_index(info.glyph_id) {
-            info.set_glyph_index(u32::from(glyph_id.0));
-        }
+        let glyph_id = face.glyph_index(info.glyph_id).unwrap_or(not_found);
+        info.set_glyph_index(u32::from(glyph_id.0));
     };
 
     // TODO: Currently if there's a variation-selector we give-up, it's just too hard.
@@ -365,8 +365,9 @@ fn decompose_current_character(ctx: &mut ShapeNormalizeContext, shortest: bool)
         }
     }
 
-    // Insert a .notdef glyph if decomposition failed.
-    ctx.buffer.next_char(0);
+    // Insert a not-found glyph if decomposition failed.
+    let not_found = ctx.buffer.not_found;
+    ctx.buffer.next_char(u32::from(not_found.0));
 }
 
 /// Returns 0 if didn't decompose, number of resulting characters otherwise.
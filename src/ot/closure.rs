@@ -0,0 +1,308 @@
+//! GSUB glyph closure.
+
+use alloc::vec::Vec;
+
+use ttf_parser::{GlyphId, LazyArray16};
+use ttf_parser::gsub::*;
+use ttf_parser::opentype_layout::*;
+
+use crate::{Face, Tag};
+use crate::glyph_set::{GlyphSet, GlyphSetBuilder};
+use super::{CoverageExt, SubstLookup, SubstitutionTable, MAX_NESTING_LEVEL};
+
+/// Computes all glyphs reachable from `glyphs` through the lookups of `GSUB` features.
+///
+/// When `features` is `None`, lookups of all features are used.
+pub fn substitute_closure(face: &Face, features: Option<&[Tag]>, glyphs: &GlyphSet) -> GlyphSet {
+    let table = match face.gsub.as_ref() {
+        Some(table) => table,
+        None => return glyphs.clone(),
+    };
+
+    let mut lookups: Vec<LookupIndex> = table.inner.features.into_iter()
+        .filter(|feature| match features {
+            Some(tags) => tags.contains(&feature.tag),
+            None => true,
+        })
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+    lookups.sort_unstable();
+    lookups.dedup();
+
+    // The set only grows, so this terminates.
+    let mut set = glyphs.clone();
+    loop {
+        let mut output = set.to_builder();
+        let mut ctx = ClosureContext {
+            table,
+            glyphs: &set,
+            output: &mut output,
+            nesting_level_left: MAX_NESTING_LEVEL,
+        };
+
+        for &index in &lookups {
+            ctx.recurse(index);
+        }
+
+        let next = output.finish();
+        if next.len() == set.len() {
+            return next;
+        }
+
+        set = next;
+    }
+}
+
+struct ClosureContext<'a, 'b> {
+    table: &'a SubstitutionTable<'b>,
+    glyphs: &'a GlyphSet,
+    output: &'a mut GlyphSetBuilder,
+    nesting_level_left: usize,
+}
+
+impl ClosureContext<'_, '_> {
+    fn recurse(&mut self, index: LookupIndex) {
+        if self.nesting_level_left == 0 {
+            return;
+        }
+
+        let table = self.table;
+        if let Some(lookup) = table.lookups.get(usize::from(index)) {
+            self.nesting_level_left -= 1;
+            lookup.closure(self);
+            self.nesting_level_left += 1;
+        }
+    }
+
+    fn recurse_all(&mut self, lookups: LazyArray16<SequenceLookupRecord>) {
+        for record in lookups {
+            self.recurse(record.lookup_list_index);
+        }
+    }
+}
+
+/// Add the glyphs a lookup can produce from the current glyph set.
+trait Closure {
+    fn closure(&self, ctx: &mut ClosureContext);
+}
+
+impl Closure for SubstLookup<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        for subtable in &self.subtables {
+            match subtable {
+                SubstitutionSubtable::Single(t) => t.closure(ctx),
+                SubstitutionSubtable::Multiple(t) => t.closure(ctx),
+                SubstitutionSubtable::Alternate(t) => t.closure(ctx),
+                SubstitutionSubtable::Ligature(t) => t.closure(ctx),
+                SubstitutionSubtable::Context(t) => t.closure(ctx),
+                SubstitutionSubtable::ChainContext(t) => t.closure(ctx),
+                SubstitutionSubtable::ReverseChainSingle(t) => t.closure(ctx),
+            }
+        }
+    }
+}
+
+impl Closure for SingleSubstitution<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        match *self {
+            Self::Format1 { coverage, delta } => {
+                for (_, glyph) in coverage.intersected(ctx.glyphs) {
+                    // Truncated to 16bit, like in `apply`.
+                    ctx.output.insert(GlyphId((i32::from(glyph.0) + i32::from(delta)) as u16));
+                }
+            }
+            Self::Format2 { coverage, substitutes } => {
+                for (index, _) in coverage.intersected(ctx.glyphs) {
+                    if let Some(glyph) = substitutes.get(index) {
+                        ctx.output.insert(glyph);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Closure for MultipleSubstitution<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        for (index, _) in self.coverage.intersected(ctx.glyphs) {
+            if let Some(sequence) = self.sequences.get(index) {
+                for glyph in sequence.substitutes {
+                    ctx.output.insert(glyph);
+                }
+            }
+        }
+    }
+}
+
+impl Closure for AlternateSubstitution<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        for (index, _) in self.coverage.intersected(ctx.glyphs) {
+            if let Some(set) = self.alternate_sets.get(index) {
+                for glyph in set.alternates {
+                    ctx.output.insert(glyph);
+                }
+            }
+        }
+    }
+}
+
+impl Closure for LigatureSubstitution<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        for (index, _) in self.coverage.intersected(ctx.glyphs) {
+            if let Some(set) = self.ligature_sets.get(index) {
+                for lig in set {
+                    if lig.components.into_iter().all(|glyph| ctx.glyphs.contains(glyph)) {
+                        ctx.output.insert(lig.glyph);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Closure for ReverseChainSingleSubstitution<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        let context_matches = self.backtrack_coverages.into_iter()
+            .chain(self.lookahead_coverages)
+            .all(|coverage| coverage.intersects(ctx.glyphs));
+
+        if !context_matches {
+            return;
+        }
+
+        for (index, _) in self.coverage.intersected(ctx.glyphs) {
+            if let Some(glyph) = self.substitutes.get(index) {
+                ctx.output.insert(glyph);
+            }
+        }
+    }
+}
+
+impl Closure for ContextLookup<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        match *self {
+            Self::Format1 { coverage, sets } => {
+                for (index, _) in coverage.intersected(ctx.glyphs) {
+                    for rule in sets.get(index).into_iter().flatten() {
+                        if values_intersect(rule.input, ctx.glyphs, intersects_glyph) {
+                            ctx.recurse_all(rule.lookups);
+                        }
+                    }
+                }
+            }
+            Self::Format2 { coverage, classes, sets } => {
+                if !coverage.intersects(ctx.glyphs) {
+                    return;
+                }
+
+                let intersects = |set: &GlyphSet, class| intersects_class(classes, set, class);
+                for class in 0..sets.len() {
+                    if !intersects(ctx.glyphs, class) {
+                        continue;
+                    }
+
+                    for rule in sets.get(class).into_iter().flatten() {
+                        if values_intersect(rule.input, ctx.glyphs, intersects) {
+                            ctx.recurse_all(rule.lookups);
+                        }
+                    }
+                }
+            }
+            Self::Format3 { coverage, coverages, lookups } => {
+                if coverage.intersects(ctx.glyphs)
+                    && coverages.into_iter().all(|coverage| coverage.intersects(ctx.glyphs))
+                {
+                    ctx.recurse_all(lookups);
+                }
+            }
+        }
+    }
+}
+
+impl Closure for ChainedContextLookup<'_> {
+    fn closure(&self, ctx: &mut ClosureContext) {
+        match *self {
+            Self::Format1 { coverage, sets } => {
+                for (index, _) in coverage.intersected(ctx.glyphs) {
+                    for rule in sets.get(index).into_iter().flatten() {
+                        if values_intersect(rule.backtrack, ctx.glyphs, intersects_glyph)
+                            && values_intersect(rule.input, ctx.glyphs, intersects_glyph)
+                            && values_intersect(rule.lookahead, ctx.glyphs, intersects_glyph)
+                        {
+                            ctx.recurse_all(rule.lookups);
+                        }
+                    }
+                }
+            }
+            Self::Format2 { coverage, backtrack_classes, input_classes, lookahead_classes, sets } => {
+                if !coverage.intersects(ctx.glyphs) {
+                    return;
+                }
+
+                let backtrack = |set: &GlyphSet, class| intersects_class(backtrack_classes, set, class);
+                let input = |set: &GlyphSet, class| intersects_class(input_classes, set, class);
+                let lookahead = |set: &GlyphSet, class| intersects_class(lookahead_classes, set, class);
+                for class in 0..sets.len() {
+                    if !input(ctx.glyphs, class) {
+                        continue;
+                    }
+
+                    for rule in sets.get(class).into_iter().flatten() {
+                        if values_intersect(rule.backtrack, ctx.glyphs, backtrack)
+                            && values_intersect(rule.input, ctx.glyphs, input)
+                            && values_intersect(rule.lookahead, ctx.glyphs, lookahead)
+                        {
+                            ctx.recurse_all(rule.lookups);
+                        }
+                    }
+                }
+            }
+            Self::Format3 { coverage, backtrack_coverages, input_coverages, lookahead_coverages, lookups } => {
+                if coverage.intersects(ctx.glyphs)
+                    && backtrack_coverages.into_iter()
+                        .chain(input_coverages)
+                        .chain(lookahead_coverages)
+                        .all(|coverage| coverage.intersects(ctx.glyphs))
+                {
+                    ctx.recurse_all(lookups);
+                }
+            }
+        }
+    }
+}
+
+fn values_intersect(
+    values: LazyArray16<u16>,
+    set: &GlyphSet,
+    intersects: impl Fn(&GlyphSet, u16) -> bool,
+) -> bool {
+    values.into_iter().all(|value| intersects(set, value))
+}
+
+fn intersects_glyph(set: &GlyphSet, glyph: u16) -> bool {
+    set.contains(GlyphId(glyph))
+}
+
+/// Checks whether any glyph of the set has the class.
+///
+/// Class 0 contains all glyphs not listed in the class definition,
+/// so it is conservatively assumed to intersect.
+fn intersects_class(classes: ClassDefinition, set: &GlyphSet, class: Class) -> bool {
+    if class == 0 {
+        return true;
+    }
+
+    match classes {
+        ClassDefinition::Format1 { start, classes } => {
+            classes.into_iter().enumerate().any(|(i, value)| {
+                value == class && set.contains(GlyphId(start.0.wrapping_add(i as u16)))
+            })
+        }
+        ClassDefinition::Format2 { records } => {
+            records.into_iter().any(|record| {
+                record.value == class
+                    && (record.start.0..=record.end.0).any(|glyph| set.contains(GlyphId(glyph)))
+            })
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! Lookup and glyph collection for layout inspection.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use ttf_parser::{FromData, GlyphId, LazyArray16, Tag};
+use ttf_parser::gpos::*;
+use ttf_parser::gsub::*;
+use ttf_parser::opentype_layout::*;
+
+use crate::Face;
+use crate::glyph_set::{GlyphSet, GlyphSetBuilder};
+use super::{CoverageExt, TableIndex, MAX_NESTING_LEVEL};
+
+/// A sorted set of lookup indices.
+///
+/// Created by [`Face::collect_lookups`].
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct LookupSet {
+    indices: Vec<LookupIndex>,
+}
+
+impl LookupSet {
+    /// Checks whether the lookup is in the set.
+    pub fn contains(&self, index: LookupIndex) -> bool {
+        self.indices.binary_search(&index).is_ok()
+    }
+
+    /// Returns the number of lookups in the set.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Checks whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Returns an iterator over the lookup indices in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = LookupIndex> + '_ {
+        self.indices.iter().copied()
+    }
+}
+
+/// Glyphs that a lookup can match and produce.
+///
+/// Created by [`Face::collect_glyphs`].
+#[derive(Clone, Debug)]
+pub struct LookupGlyphs {
+    /// Glyphs that can appear in the backtrack context.
+    pub before: GlyphSet,
+    /// Glyphs that can appear in the input sequence.
+    pub input: GlyphSet,
+    /// Glyphs that can appear in the lookahead context.
+    pub after: GlyphSet,
+    /// Glyphs that substitutions can produce, including by nested lookups.
+    ///
+    /// Always empty for `GPOS`.
+    pub output: GlyphSet,
+}
+
+pub(crate) fn collect_lookups(
+    face: &Face,
+    table_index: TableIndex,
+    scripts: Option<&[Tag]>,
+    languages: Option<&[Tag]>,
+    features: Option<&[Tag]>,
+) -> LookupSet {
+    let table = match face.layout_table(table_index) {
+        Some(table) => table,
+        None => return LookupSet::default(),
+    };
+
+    let mut feature_indices = Vec::new();
+    for script in table.scripts {
+        if let Some(tags) = scripts {
+            if !tags.contains(&script.tag) {
+                continue;
+            }
+        }
+
+        match languages {
+            Some(tags) => {
+                for &tag in tags {
+                    if let Some(sys) = script.languages.find(tag) {
+                        collect_features(table, sys, features, &mut feature_indices);
+                    }
+                }
+            }
+            None => {
+                let systems = script.default_language.into_iter().chain(script.languages);
+                for sys in systems {
+                    collect_features(table, sys, features, &mut feature_indices);
+                }
+            }
+        }
+    }
+
+    feature_indices.sort_unstable();
+    feature_indices.dedup();
+
+    // Use the feature substitutes for the current variation coordinates, like during shaping.
+    let coords = face.variation_coordinates();
+    let variation_index = table.variations.and_then(|var| var.find_index(coords));
+
+    let mut indices = Vec::new();
+    for feature_index in feature_indices {
+        let feature = variation_index
+            .and_then(|idx| table.variations?.find_substitute(feature_index, idx))
+            .or_else(|| table.features.get(feature_index));
+
+        if let Some(feature) = feature {
+            indices.extend(feature.lookup_indices.into_iter().filter(|&i| i < table.lookups.len()));
+        }
+    }
+
+    indices.sort_unstable();
+    indices.dedup();
+    LookupSet { indices }
+}
+
+/// Collects features of a language system.
+///
+/// The required feature is included only when all features are requested.
+fn collect_features(
+    table: &LayoutTable,
+    sys: LanguageSystem,
+    features: Option<&[Tag]>,
+    indices: &mut Vec<FeatureIndex>,
+) {
+    match features {
+        Some(tags) => {
+            for index in sys.feature_indices {
+                if let Some(feature) = table.features.get(index) {
+                    if tags.contains(&feature.tag) {
+                        indices.push(index);
+                    }
+                }
+            }
+        }
+        None => {
+            indices.extend(sys.required_feature);
+            indices.extend(sys.feature_indices);
+        }
+    }
+}
+
+pub(crate) fn collect_glyphs(face: &Face, table_index: TableIndex, lookup_index: LookupIndex) -> LookupGlyphs {
+    let mut ctx = CollectGlyphsContext {
+        face,
+        table_index,
+        subtable_data: None,
+        before: GlyphSet::builder(),
+        input: GlyphSet::builder(),
+        after: GlyphSet::builder(),
+        output: GlyphSet::builder(),
+        nesting_level_left: MAX_NESTING_LEVEL,
+        visited: Vec::new(),
+    };
+
+    match table_index {
+        TableIndex::GSUB => ctx.recurse(lookup_index),
+        TableIndex::GPOS => {
+            if let Some(lookup) = face.gpos.as_ref().and_then(|t| t.lookups.get(usize::from(lookup_index))) {
+                for (i, subtable) in lookup.subtables.iter().enumerate() {
+                    ctx.subtable_data = lookup.subtables_data.get(i).copied();
+                    subtable.collect_glyphs(&mut ctx);
+                }
+            }
+        }
+    }
+
+    LookupGlyphs {
+        before: ctx.before.finish(),
+        input: ctx.input.finish(),
+        after: ctx.after.finish(),
+        output: ctx.output.finish(),
+    }
+}
+
+struct CollectGlyphsContext<'a> {
+    face: &'a Face<'a>,
+    table_index: TableIndex,
+    /// Raw data of the current top-level `GPOS` subtable.
+    subtable_data: Option<&'a [u8]>,
+    before: GlyphSetBuilder,
+    input: GlyphSetBuilder,
+    after: GlyphSetBuilder,
+    output: GlyphSetBuilder,
+    nesting_level_left: usize,
+    visited: Vec<LookupIndex>,
+}
+
+impl CollectGlyphsContext<'_> {
+    /// Collects a `GSUB` lookup.
+    ///
+    /// Only the output of nested lookups matters, since their input
+    /// is already part of the context that invokes them. Nested `GPOS` lookups
+    /// produce no glyphs, so they are skipped.
+    fn recurse(&mut self, index: LookupIndex) {
+        if self.table_index == TableIndex::GPOS
+            || self.nesting_level_left == 0
+            || self.visited.contains(&index)
+        {
+            return;
+        }
+
+        let face = self.face;
+        let lookup = match face.gsub.as_ref().and_then(|t| t.lookups.get(usize::from(index))) {
+            Some(lookup) => lookup,
+            None => return,
+        };
+
+        self.visited.push(index);
+        let nested = self.nesting_level_left != MAX_NESTING_LEVEL;
+        let saved = if nested {
+            Some((
+                mem::replace(&mut self.before, GlyphSet::builder()),
+                mem::replace(&mut self.input, GlyphSet::builder()),
+                mem::replace(&mut self.after, GlyphSet::builder()),
+            ))
+        } else {
+            None
+        };
+
+        self.nesting_level_left -= 1;
+        for subtable in &lookup.subtables {
+            subtable.collect_glyphs(self);
+        }
+        self.nesting_level_left += 1;
+
+        if let Some((before, input, after)) = saved {
+            self.before = before;
+            self.input = input;
+            self.after = after;
+        }
+    }
+
+    fn recurse_all(&mut self, lookups: LazyArray16<SequenceLookupRecord>) {
+        for record in lookups {
+            self.recurse(record.lookup_list_index);
+        }
+    }
+}
+
+/// Add glyphs a lookup subtable can match and produce.
+trait CollectGlyphs {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext);
+}
+
+impl CollectGlyphs for SubstitutionSubtable<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match self {
+            Self::Single(t) => t.collect_glyphs(ctx),
+            Self::Multiple(t) => t.collect_glyphs(ctx),
+            Self::Alternate(t) => t.collect_glyphs(ctx),
+            Self::Ligature(t) => t.collect_glyphs(ctx),
+            Self::Context(t) => t.collect_glyphs(ctx),
+            Self::ChainContext(t) => t.collect_glyphs(ctx),
+            Self::ReverseChainSingle(t) => t.collect_glyphs(ctx),
+        }
+    }
+}
+
+impl CollectGlyphs for PositioningSubtable<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match self {
+            Self::Single(t) => t.coverage().collect(&mut ctx.input),
+            Self::Pair(t) => t.collect_glyphs(ctx),
+            Self::Cursive(t) => t.coverage.collect(&mut ctx.input),
+            Self::MarkToBase(t) => {
+                t.mark_coverage.collect(&mut ctx.input);
+                t.base_coverage.collect(&mut ctx.input);
+            }
+            Self::MarkToLigature(t) => {
+                t.mark_coverage.collect(&mut ctx.input);
+                t.ligature_coverage.collect(&mut ctx.input);
+            }
+            Self::MarkToMark(t) => {
+                t.mark1_coverage.collect(&mut ctx.input);
+                t.mark2_coverage.collect(&mut ctx.input);
+            }
+            Self::Context(t) => t.collect_glyphs(ctx),
+            Self::ChainContext(t) => t.collect_glyphs(ctx),
+        }
+    }
+}
+
+impl CollectGlyphs for SingleSubstitution<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match *self {
+            Self::Format1 { coverage, delta } => {
+                let mut covered = GlyphSet::builder();
+                coverage.collect(&mut covered);
+                for glyph in covered.finish().iter() {
+                    ctx.input.insert(glyph);
+                    // Truncated to 16bit, like in `apply`.
+                    ctx.output.insert(GlyphId((i32::from(glyph.0) + i32::from(delta)) as u16));
+                }
+            }
+            Self::Format2 { coverage, substitutes } => {
+                coverage.collect(&mut ctx.input);
+                for glyph in substitutes {
+                    ctx.output.insert(glyph);
+                }
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for MultipleSubstitution<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        self.coverage.collect(&mut ctx.input);
+        for sequence in self.sequences {
+            for glyph in sequence.substitutes {
+                ctx.output.insert(glyph);
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for AlternateSubstitution<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        self.coverage.collect(&mut ctx.input);
+        for set in self.alternate_sets {
+            for glyph in set.alternates {
+                ctx.output.insert(glyph);
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for LigatureSubstitution<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        self.coverage.collect(&mut ctx.input);
+        for set in self.ligature_sets {
+            for lig in set {
+                for glyph in lig.components {
+                    ctx.input.insert(glyph);
+                }
+                ctx.output.insert(lig.glyph);
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for ReverseChainSingleSubstitution<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        self.coverage.collect(&mut ctx.input);
+        for coverage in self.backtrack_coverages {
+            coverage.collect(&mut ctx.before);
+        }
+        for coverage in self.lookahead_coverages {
+            coverage.collect(&mut ctx.after);
+        }
+        for glyph in self.substitutes {
+            ctx.output.insert(glyph);
+        }
+    }
+}
+
+impl CollectGlyphs for PairAdjustment<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match *self {
+            Self::Format1 { coverage, .. } => {
+                coverage.collect(&mut ctx.input);
+                if let Some(data) = ctx.subtable_data {
+                    collect_pair_set_glyphs(data, &mut ctx.input);
+                }
+            }
+            Self::Format2 { coverage, classes, .. } => {
+                coverage.collect(&mut ctx.input);
+                collect_classes(classes.1, None, &mut ctx.input);
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for ContextLookup<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match *self {
+            Self::Format1 { coverage, sets } => {
+                coverage.collect(&mut ctx.input);
+                for rule in sets.into_iter().flatten() {
+                    collect_glyph_values(rule.input, &mut ctx.input);
+                    ctx.recurse_all(rule.lookups);
+                }
+            }
+            Self::Format2 { coverage, classes, sets } => {
+                coverage.collect(&mut ctx.input);
+                for rule in sets.into_iter().flatten() {
+                    collect_class_values(classes, rule.input, &mut ctx.input);
+                    ctx.recurse_all(rule.lookups);
+                }
+            }
+            Self::Format3 { coverage, coverages, lookups } => {
+                coverage.collect(&mut ctx.input);
+                for coverage in coverages {
+                    coverage.collect(&mut ctx.input);
+                }
+                ctx.recurse_all(lookups);
+            }
+        }
+    }
+}
+
+impl CollectGlyphs for ChainedContextLookup<'_> {
+    fn collect_glyphs(&self, ctx: &mut CollectGlyphsContext) {
+        match *self {
+            Self::Format1 { coverage, sets } => {
+                coverage.collect(&mut ctx.input);
+                for rule in sets.into_iter().flatten() {
+                    collect_glyph_values(rule.backtrack, &mut ctx.before);
+                    collect_glyph_values(rule.input, &mut ctx.input);
+                    collect_glyph_values(rule.lookahead, &mut ctx.after);
+                    ctx.recurse_all(rule.lookups);
+                }
+            }
+            Self::Format2 { coverage, backtrack_classes, input_classes, lookahead_classes, sets } => {
+                coverage.collect(&mut ctx.input);
+                for rule in sets.into_iter().flatten() {
+                    collect_class_values(backtrack_classes, rule.backtrack, &mut ctx.before);
+                    collect_class_values(input_classes, rule.input, &mut ctx.input);
+                    collect_class_values(lookahead_classes, rule.lookahead, &mut ctx.after);
+                    ctx.recurse_all(rule.lookups);
+                }
+            }
+            Self::Format3 { coverage, backtrack_coverages, input_coverages, lookahead_coverages, lookups } => {
+                coverage.collect(&mut ctx.input);
+                for coverage in backtrack_coverages {
+                    coverage.collect(&mut ctx.before);
+                }
+                for coverage in input_coverages {
+                    coverage.collect(&mut ctx.input);
+                }
+                for coverage in lookahead_coverages {
+                    coverage.collect(&mut ctx.after);
+                }
+                ctx.recurse_all(lookups);
+            }
+        }
+    }
+}
+
+fn collect_glyph_values(values: LazyArray16<u16>, set: &mut GlyphSetBuilder) {
+    for value in values {
+        set.insert(GlyphId(value));
+    }
+}
+
+fn collect_class_values(classes: ClassDefinition, values: LazyArray16<u16>, set: &mut GlyphSetBuilder) {
+    for class in values {
+        collect_classes(classes, Some(class), set);
+    }
+}
+
+/// Collects the second glyphs of a pair adjustment format 1 subtable.
+fn collect_pair_set_glyphs(data: &[u8], set: &mut GlyphSetBuilder) -> Option<()> {
+    let read = |offset: usize| data.get(offset..offset + 2).and_then(u16::parse);
+    let value_record_len = |format: u16| (format & 0xFF).count_ones() as usize * 2;
+
+    let record_len = 2 + value_record_len(read(4)?) + value_record_len(read(6)?);
+    let count = usize::from(read(8)?);
+    for i in 0..count {
+        // Zero offsets must be ignored.
+        let pair_set = usize::from(read(10 + i * 2)?);
+        if pair_set == 0 {
+            continue;
+        }
+
+        for j in 0..usize::from(read(pair_set)?) {
+            set.insert(GlyphId(read(pair_set + 2 + j * record_len)?));
+        }
+    }
+
+    Some(())
+}
+
+/// Collects glyphs of a class, or of all classes when `class` is `None`.
+///
+/// Class 0 contains all glyphs not listed in the class definition,
+/// so only glyphs listed with class 0 are collected for it.
+fn collect_classes(classes: ClassDefinition, class: Option<Class>, set: &mut GlyphSetBuilder) {
+    let matches = |value: Class| match class {
+        Some(class) => value == class,
+        None => value != 0,
+    };
+    match classes {
+        ClassDefinition::Format1 { start, classes } => {
+            for (i, value) in classes.into_iter().enumerate() {
+                if matches(value) {
+                    set.insert(GlyphId(start.0.wrapping_add(i as u16)));
+                }
+            }
+        }
+        ClassDefinition::Format2 { records } => {
+            for record in records {
+                if matches(record.value) {
+                    set.insert_range(record.start..=record.end);
+                }
+            }
+        }
+    }
+}
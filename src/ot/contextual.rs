@@ -5,7 +5,7 @@ use super::MAX_CONTEXT_LENGTH;
 use super::apply::{Apply, ApplyContext, WouldApply, WouldApplyContext};
 use super::matching::{
     match_backtrack, match_glyph, match_input, match_lookahead,
-    MatchFunc, Matched,
+    MatchFunc, Matched, MatchingFunc,
 };
 
 impl WouldApply for ContextLookup<'_> {
@@ -56,10 +56,17 @@ impl Apply for ContextLookup<'_> {
                     coverage.get(glyph).is_some()
                 };
 
-                match_input(ctx, coverages_len as u16, &match_func).map(|matched| {
-                    ctx.buffer.unsafe_to_break(ctx.buffer.idx, ctx.buffer.idx + matched.len);
-                    apply_lookup(ctx, usize::from(coverages_len), matched, lookups);
-                })
+                match match_input(ctx, coverages_len as u16, &match_func) {
+                    Ok(matched) => {
+                        ctx.buffer.unsafe_to_break(ctx.buffer.idx, ctx.buffer.idx + matched.len);
+                        apply_lookup(ctx, usize::from(coverages_len), matched, lookups);
+                        Some(())
+                    }
+                    Err(end) => {
+                        ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+                        None
+                    }
+                }
             }
         }
     }
@@ -165,17 +172,12 @@ impl Apply for ChainedContextLookup<'_> {
                     coverage.contains(glyph)
                 };
 
-                if let Some(matched) = match_input(ctx, input_coverages.len(), &input) {
-                    if let Some(start_idx) = match_backtrack(ctx, backtrack_coverages.len(), &back) {
-                        if let Some(end_idx) = match_lookahead(ctx, lookahead_coverages.len(), &ahead, matched.len) {
-                            ctx.buffer.unsafe_to_break_from_outbuffer(start_idx, end_idx);
-                            apply_lookup(ctx, usize::from(input_coverages.len()), matched, lookups);
-                            return Some(());
-                        }
-                    }
-                }
-
-                None
+                apply_chain_context_impl(
+                    ctx,
+                    [&back, &input, &ahead],
+                    [backtrack_coverages.len(), input_coverages.len(), lookahead_coverages.len()],
+                    lookups,
+                )
             }
         }
     }
@@ -236,10 +238,17 @@ fn apply_context(
         match_func(glyph, value)
     };
 
-    match_input(ctx, input.len(), &match_func).map(|matched| {
-        ctx.buffer.unsafe_to_break(ctx.buffer.idx, ctx.buffer.idx + matched.len);
-        apply_lookup(ctx, usize::from(input.len()), matched, lookups);
-    })
+    match match_input(ctx, input.len(), &match_func) {
+        Ok(matched) => {
+            ctx.buffer.unsafe_to_break(ctx.buffer.idx, ctx.buffer.idx + matched.len);
+            apply_lookup(ctx, usize::from(input.len()), matched, lookups);
+            Some(())
+        }
+        Err(end) => {
+            ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+            None
+        }
+    }
 }
 
 fn apply_chain_context(
@@ -268,17 +277,48 @@ fn apply_chain_context(
         match_funcs[1](glyph, value)
     };
 
-    if let Some(matched) = match_input(ctx, input.len(), &f3) {
-        if let Some(start_idx) = match_backtrack(ctx, backtrack.len(), &f1) {
-            if let Some(end_idx) = match_lookahead(ctx, lookahead.len(), &f2, matched.len) {
-                ctx.buffer.unsafe_to_break_from_outbuffer(start_idx, end_idx);
-                apply_lookup(ctx, usize::from(input.len()), matched, lookups);
-                return Some(());
+    apply_chain_context_impl(
+        ctx,
+        [&f1, &f3, &f2],
+        [backtrack.len(), input.len(), lookahead.len()],
+        lookups,
+    )
+}
+
+// Match functions and lengths are in backtrack, input, lookahead order.
+fn apply_chain_context_impl(
+    ctx: &mut ApplyContext,
+    match_funcs: [&MatchingFunc; 3],
+    lens: [u16; 3],
+    lookups: LazyArray16<SequenceLookupRecord>,
+) -> Option<()> {
+    let (matched, end_idx) = match match_input(ctx, lens[1], match_funcs[1]) {
+        Ok(matched) => {
+            match match_lookahead(ctx, lens[2], match_funcs[2], matched.len) {
+                Ok(end_idx) => (matched, end_idx),
+                Err(end) => {
+                    ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+                    return None;
+                }
             }
         }
-    }
+        Err(end) => {
+            ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+            return None;
+        }
+    };
+
+    let start_idx = match match_backtrack(ctx, lens[0], match_funcs[0]) {
+        Ok(start_idx) => start_idx,
+        Err(start) => {
+            ctx.buffer.unsafe_to_concat_from_outbuffer(start, end_idx);
+            return None;
+        }
+    };
 
-    None
+    ctx.buffer.unsafe_to_break_from_outbuffer(start_idx, end_idx);
+    apply_lookup(ctx, usize::from(lens[1]), matched, lookups);
+    Some(())
 }
 
 fn apply_lookup(
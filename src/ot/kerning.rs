@@ -66,7 +66,7 @@ pub fn kern(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
         }
 
         if subtable.has_state_machine {
-            apply_state_machine_kerning(&subtable, plan.kern_mask, buffer);
+            apply_state_machine_kerning(&subtable, face, plan.kern_mask, buffer);
         } else {
             if !plan.requested_kerning {
                 continue;
@@ -103,7 +103,9 @@ fn machine_kern(
         }
 
         let mut iter = SkippyIter::new(&ctx, i, 1, false);
-        if !iter.next() {
+        let mut unsafe_to = 0;
+        if !iter.next(Some(&mut unsafe_to)) {
+            ctx.buffer.unsafe_to_concat(i, unsafe_to);
             i += 1;
             continue;
         }
@@ -115,6 +117,7 @@ fn machine_kern(
 
         let pos = &mut ctx.buffer.pos;
         if kern != 0 {
+            let kern = if horizontal { ctx.face.em_scale_x(kern) } else { ctx.face.em_scale_y(kern) };
             if horizontal {
                 if cross_stream {
                     pos[j].y_offset = kern;
@@ -140,6 +143,8 @@ fn machine_kern(
             }
 
             ctx.buffer.unsafe_to_break(i, j + 1)
+        } else {
+            ctx.buffer.unsafe_to_concat(i, j + 1);
         }
 
         i = j;
@@ -165,6 +170,7 @@ struct StateMachineDriver {
 
 fn apply_state_machine_kerning(
     subtable: &kern::Subtable,
+    face: &Face,
     kern_mask: Mask,
     buffer: &mut Buffer,
 ) {
@@ -219,7 +225,7 @@ fn apply_state_machine_kerning(
         }
 
         state_machine_transition(entry, subtable.has_cross_stream, kern_mask,
-                                 state_table, &mut driver, buffer);
+                                 state_table, &mut driver, face, buffer);
 
         state = state_table.new_state(entry.new_state);
 
@@ -240,6 +246,7 @@ fn state_machine_transition(
     kern_mask: Mask,
     state_table: &apple_layout::StateTable,
     driver: &mut StateMachineDriver,
+    face: &Face,
     buffer: &mut Buffer,
 ) {
     if entry.has_push() {
@@ -296,12 +303,12 @@ fn state_machine_transition(
                         pos.set_attach_chain(0);
                         pos.y_offset = 0;
                     } else if pos.attach_type() != 0 {
-                        pos.y_offset += v;
+                        pos.y_offset += face.em_scale_y(v);
                         has_gpos_attachment = true;
                     }
                 } else if glyph_mask & kern_mask != 0 {
-                    pos.x_advance += v;
-                    pos.x_offset += v;
+                    pos.x_advance += face.em_scale_x(v);
+                    pos.x_offset += face.em_scale_x(v);
                 }
             } else {
                 if has_cross_stream {
@@ -311,13 +318,13 @@ fn state_machine_transition(
                         pos.set_attach_chain(0);
                         pos.x_offset = 0;
                     } else if pos.attach_type() != 0 {
-                        pos.x_offset += v;
+                        pos.x_offset += face.em_scale_x(v);
                         has_gpos_attachment = true;
                     }
                 } else if glyph_mask & kern_mask != 0 {
                     if pos.y_offset == 0 {
-                        pos.y_advance += v;
-                        pos.y_offset += v;
+                        pos.y_advance += face.em_scale_y(v);
+                        pos.y_offset += face.em_scale_y(v);
                     }
                 }
             }
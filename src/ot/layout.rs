@@ -14,13 +14,17 @@ use super::apply::{Apply, ApplyContext};
 pub const MAX_NESTING_LEVEL: usize = 6;
 pub const MAX_CONTEXT_LENGTH: usize = 64;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An OpenType layout table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TableIndex {
+    /// Glyph substitution table.
     GSUB = 0,
+    /// Glyph positioning table.
     GPOS = 1,
 }
 
 impl TableIndex {
+    /// Returns an iterator over all tables.
     pub fn iter() -> impl Iterator<Item = TableIndex> {
         [Self::GSUB, Self::GPOS].iter().copied()
     }
@@ -183,10 +187,19 @@ pub fn apply_layout_table<T: LayoutTable>(
     buffer: &mut Buffer,
     table: Option<&T>,
 ) {
+    if !buffer.message(face, format_args!("start table {:?}", T::INDEX)) {
+        return;
+    }
+
     let mut ctx = ApplyContext::new(T::INDEX, face, buffer);
 
     for (stage_index, stage) in plan.ot_map.stages(T::INDEX).iter().enumerate() {
         for lookup in plan.ot_map.stage_lookups(T::INDEX, stage_index) {
+            let (index, tag) = (lookup.index, lookup.feature_tag);
+            if !ctx.buffer.message(face, format_args!("start lookup {} feature '{}'", index, tag)) {
+                continue;
+            }
+
             ctx.lookup_index = lookup.index;
             ctx.lookup_mask = lookup.mask;
             ctx.auto_zwj = lookup.auto_zwj;
@@ -202,13 +215,20 @@ pub fn apply_layout_table<T: LayoutTable>(
                     apply_string::<T>(&mut ctx, lookup);
                 }
             }
+
+            ctx.buffer.message(face, format_args!("end lookup {} feature '{}'", index, tag));
         }
 
         if let Some(func) = stage.pause_func {
             ctx.buffer.clear_output();
-            func(plan, face, ctx.buffer);
+            if ctx.buffer.message(face, format_args!("start pause at stage {}", stage_index)) {
+                func(plan, face, ctx.buffer);
+                ctx.buffer.message(face, format_args!("end pause at stage {}", stage_index));
+            }
         }
     }
+
+    ctx.buffer.message(face, format_args!("end table {:?}", T::INDEX));
 }
 
 fn apply_string<T: LayoutTable>(ctx: &mut ApplyContext, lookup: &T::Lookup) {
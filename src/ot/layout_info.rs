@@ -0,0 +1,216 @@
+//! OpenType layout introspection.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ttf_parser::opentype_layout::{FeatureIndex, LanguageSystem, LayoutTable};
+use ttf_parser::{FromData, Tag};
+
+use crate::Face;
+use super::TableIndex;
+
+// https://docs.microsoft.com/en-us/typography/opentype/spec/name#windows-language-ids
+const WINDOWS_ENGLISH_US_LANGUAGE: u16 = 0x0409;
+
+/// OpenType layout information of a face.
+///
+/// Lists scripts, language systems and features of the `GSUB` and `GPOS` tables.
+/// Created by [`Face::layout`].
+#[derive(Clone, Copy)]
+pub struct Layout<'a> {
+    face: &'a Face<'a>,
+}
+
+/// User interface names of a feature.
+///
+/// Only character variant (`cvXX`) features have all of them.
+/// Stylistic set (`ssXX`) features have only a label.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct FeatureNames {
+    /// Feature label.
+    pub label: Option<String>,
+    /// Feature tooltip.
+    pub tooltip: Option<String>,
+    /// Sample text that illustrates the feature.
+    pub sample: Option<String>,
+    /// Labels of the feature parameters, i.e. of the alternates.
+    pub parameters: Vec<String>,
+    /// Characters the feature applies to.
+    pub characters: Vec<char>,
+}
+
+impl<'a> Layout<'a> {
+    #[inline]
+    pub(crate) fn new(face: &'a Face<'a>) -> Self {
+        Layout { face }
+    }
+
+    /// Returns tags of all scripts in the table.
+    pub fn script_tags(&self, table: TableIndex) -> Vec<Tag> {
+        match self.face.layout_table(table) {
+            Some(table) => table.scripts.into_iter().map(|script| script.tag).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns tags of all language systems of a script.
+    ///
+    /// The default language system of the script is not included.
+    pub fn language_tags(&self, table: TableIndex, script: Tag) -> Vec<Tag> {
+        let script = match self.face.layout_table(table).and_then(|table| table.scripts.find(script)) {
+            Some(script) => script,
+            None => return Vec::new(),
+        };
+
+        script.languages.into_iter().map(|lang| lang.tag).collect()
+    }
+
+    /// Returns tags of all features in the table, without duplicates.
+    pub fn feature_tags(&self, table: TableIndex) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        if let Some(table) = self.face.layout_table(table) {
+            for feature in table.features {
+                if !tags.contains(&feature.tag) {
+                    tags.push(feature.tag);
+                }
+            }
+        }
+
+        tags
+    }
+
+    /// Returns tags of features of a language system.
+    ///
+    /// When `language` is `None`, the default language system of the script is used.
+    /// The required feature of the language system is not included.
+    pub fn language_feature_tags(&self, table: TableIndex, script: Tag, language: Option<Tag>) -> Vec<Tag> {
+        let table = match self.face.layout_table(table) {
+            Some(table) => table,
+            None => return Vec::new(),
+        };
+
+        match find_language_system(table, script, language) {
+            Some(sys) => sys.feature_indices.into_iter()
+                .filter_map(|index| table.features.get(index))
+                .map(|feature| feature.tag)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the required feature tag of a language system, if any.
+    ///
+    /// When `language` is `None`, the default language system of the script is used.
+    pub fn required_feature_tag(&self, table: TableIndex, script: Tag, language: Option<Tag>) -> Option<Tag> {
+        let table = self.face.layout_table(table)?;
+        let sys = find_language_system(table, script, language)?;
+        Some(table.features.get(sys.required_feature?)?.tag)
+    }
+
+    /// Returns user interface names of a feature from the `name` table.
+    ///
+    /// Only stylistic set (`ssXX`) and character variant (`cvXX`) features
+    /// have names. When a feature is listed several times, the first one
+    /// with names is used.
+    pub fn feature_names(&self, table: TableIndex, feature: Tag) -> Option<FeatureNames> {
+        let features = self.face.layout_table(table)?.features;
+        (0..features.len())
+            .filter(|&index| features.get(index).map(|f| f.tag) == Some(feature))
+            .find_map(|index| self.feature_names_impl(table, feature, index))
+    }
+
+    fn feature_names_impl(&self, table: TableIndex, feature: Tag, index: FeatureIndex) -> Option<FeatureNames> {
+        let bytes = feature.to_bytes();
+        let is_stylistic_set = &bytes[..2] == b"ss" && bytes[2..].iter().all(u8::is_ascii_digit);
+        let is_character_variant = &bytes[..2] == b"cv" && bytes[2..].iter().all(u8::is_ascii_digit);
+        if !is_stylistic_set && !is_character_variant {
+            return None;
+        }
+
+        let params = feature_params(self.face, table, index)?;
+        let read = |offset: usize| params.get(offset..offset + 2).and_then(u16::parse);
+
+        let mut names = FeatureNames::default();
+        if is_stylistic_set {
+            // https://docs.microsoft.com/en-us/typography/opentype/spec/features_pt#ss01
+            names.label = self.name(read(2)?);
+        } else {
+            // https://docs.microsoft.com/en-us/typography/opentype/spec/features_ae#cv01-cv99
+            names.label = self.name(read(2)?);
+            names.tooltip = self.name(read(4)?);
+            names.sample = self.name(read(6)?);
+
+            let count = read(8)?;
+            let first = read(10)?;
+            if first != 0 {
+                names.parameters = (0..count)
+                    .filter_map(|i| self.name(first.checked_add(i)?))
+                    .collect();
+            }
+
+            let char_count = usize::from(read(12)?);
+            names.characters = (0..char_count)
+                .filter_map(|i| {
+                    let offset = 14 + i * 3;
+                    let b = params.get(offset..offset + 3)?;
+                    char::from_u32(u32::from_be_bytes([0, b[0], b[1], b[2]]))
+                })
+                .collect();
+        }
+
+        Some(names)
+    }
+
+    /// Returns a Unicode string from the `name` table.
+    ///
+    /// English names are preferred. Name ID 0 means no name in feature parameters.
+    fn name(&self, name_id: u16) -> Option<String> {
+        if name_id == 0 {
+            return None;
+        }
+
+        let names = self.face.names();
+        let name = names.into_iter()
+            .filter(|name| name.name_id == name_id && name.is_unicode())
+            .find(|name| name.language_id == WINDOWS_ENGLISH_US_LANGUAGE)
+            .or_else(|| names.into_iter().find(|name| name.name_id == name_id && name.is_unicode()))?;
+
+        let units = name.name.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]]));
+        char::decode_utf16(units).collect::<Result<String, _>>().ok()
+    }
+}
+
+fn find_language_system<'a>(
+    table: &LayoutTable<'a>,
+    script: Tag,
+    language: Option<Tag>,
+) -> Option<LanguageSystem<'a>> {
+    let script = table.scripts.find(script)?;
+    match language {
+        Some(tag) => script.languages.find(tag),
+        None => script.default_language,
+    }
+}
+
+/// Returns the raw feature parameters of a feature.
+///
+/// `ttf-parser` doesn't parse them, so we have to read the table ourselves.
+fn feature_params<'a>(face: &Face<'a>, table: TableIndex, index: FeatureIndex) -> Option<&'a [u8]> {
+    let tag = match table {
+        TableIndex::GSUB => b"GSUB",
+        TableIndex::GPOS => b"GPOS",
+    };
+
+    let data = face.raw_face().table(Tag::from_bytes(tag))?;
+    let read = |offset: usize| data.get(offset..offset + 2).and_then(u16::parse).map(usize::from);
+
+    let feature_list = read(6)?;
+    let record = feature_list + 2 + usize::from(index) * 6;
+    let feature = feature_list + read(record + 4)?;
+    let params = read(feature)?;
+    if params == 0 {
+        return None;
+    }
+
+    data.get(feature + params..)
+}
@@ -35,6 +35,7 @@ pub struct FeatureMap {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LookupMap {
     pub index: LookupIndex,
+    pub feature_tag: Tag,
     // TODO: to bitflags
     pub auto_zwnj: bool,
     pub auto_zwj: bool,
@@ -526,6 +527,8 @@ impl<'a> MapBuilder<'a> {
         let table = self.face.layout_table(table_index)?;
 
         let lookup_count = table.lookups.len();
+        // Feature variation substitutes don't have a tag.
+        let feature_tag = table.features.get(feature_index)?.tag;
         let feature = match variation_index {
             Some(idx) => {
                 table.variations
@@ -537,7 +540,7 @@ impl<'a> MapBuilder<'a> {
 
         for index in feature.lookup_indices {
             if index < lookup_count {
-                lookups.push(LookupMap { mask, index, auto_zwnj, auto_zwj, random });
+                lookups.push(LookupMap { index, feature_tag, mask, auto_zwnj, auto_zwj, random });
             }
         }
 
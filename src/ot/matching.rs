@@ -3,7 +3,7 @@
 use ttf_parser::GlyphId;
 
 use crate::Mask;
-use crate::buffer::GlyphInfo;
+use crate::buffer::{BufferFlags, GlyphInfo};
 use super::{TableIndex, MAX_CONTEXT_LENGTH};
 use super::apply::ApplyContext;
 
@@ -21,11 +21,12 @@ pub struct Matched {
     pub total_component_count: u8,
 }
 
+/// On failure, returns the end of the range that is unsafe to concat.
 pub fn match_input(
     ctx: &ApplyContext,
     input_len: u16,
     match_func: &MatchingFunc,
-) -> Option<Matched> {
+) -> Result<Matched, usize> {
     // This is perhaps the trickiest part of OpenType...  Remarks:
     //
     // - If all components of the ligature were marks, we call this a mark ligature.
@@ -57,7 +58,7 @@ pub fn match_input(
 
     let count = usize::from(input_len) + 1;
     if count > MAX_CONTEXT_LENGTH {
-        return None;
+        return Err(ctx.buffer.idx + 1);
     }
 
     let mut iter = SkippyIter::new(ctx, ctx.buffer.idx, input_len, false);
@@ -73,8 +74,9 @@ pub fn match_input(
     positions[0] = ctx.buffer.idx;
 
     for position in &mut positions[1..count] {
-        if !iter.next() {
-            return None;
+        let mut unsafe_to = 0;
+        if !iter.next(Some(&mut unsafe_to)) {
+            return Err(unsafe_to);
         }
 
         *position = iter.index();
@@ -111,7 +113,7 @@ pub fn match_input(
                 }
 
                 if ligbase == Ligbase::MayNotSkip {
-                    return None;
+                    return Err(iter.index() + 1);
                 }
             }
         } else {
@@ -119,53 +121,57 @@ pub fn match_input(
             // all subsequent components should also NOT be attached to any ligature
             // component, unless they are attached to the first component itself!
             if this_lig_id != 0 && this_lig_comp != 0 && (this_lig_id != first_lig_id) {
-                return None;
+                return Err(iter.index() + 1);
             }
         }
 
         total_component_count += this.lig_num_comps();
     }
 
-    Some(Matched {
+    Ok(Matched {
         len: iter.index() - ctx.buffer.idx + 1,
         positions,
         total_component_count,
     })
 }
 
+/// On failure, returns the start of the out-buffer range that is unsafe to concat.
 pub fn match_backtrack(
     ctx: &ApplyContext,
     backtrack_len: u16,
     match_func: &MatchingFunc
-) -> Option<usize> {
+) -> Result<usize, usize> {
     let mut iter = SkippyIter::new(ctx, ctx.buffer.backtrack_len(), backtrack_len, true);
     iter.enable_matching(match_func);
 
     for _ in 0..backtrack_len {
-        if !iter.prev() {
-            return None;
+        let mut unsafe_from = 0;
+        if !iter.prev(Some(&mut unsafe_from)) {
+            return Err(unsafe_from);
         }
     }
 
-    Some(iter.index())
+    Ok(iter.index())
 }
 
+/// On failure, returns the end of the range that is unsafe to concat.
 pub fn match_lookahead(
     ctx: &ApplyContext,
     lookahead_len: u16,
     match_func: &MatchingFunc,
     offset: usize,
-) -> Option<usize> {
+) -> Result<usize, usize> {
     let mut iter = SkippyIter::new(ctx, ctx.buffer.idx + offset - 1, lookahead_len, true);
     iter.enable_matching(match_func);
 
     for _ in 0..lookahead_len {
-        if !iter.next() {
-            return None;
+        let mut unsafe_to = 0;
+        if !iter.next(Some(&mut unsafe_to)) {
+            return Err(unsafe_to);
         }
     }
 
-    Some(iter.index() + 1)
+    Ok(iter.index() + 1)
 }
 
 pub type MatchingFunc<'a> = dyn Fn(GlyphId, u16) -> bool + 'a;
@@ -222,9 +228,17 @@ impl<'a, 'b> SkippyIter<'a, 'b> {
         self.buf_idx
     }
 
-    pub fn next(&mut self) -> bool {
+    /// On failure, `unsafe_to` is set to the end of the range that is unsafe to concat.
+    pub fn next(&mut self, unsafe_to: Option<&mut usize>) -> bool {
         assert!(self.num_items > 0);
-        while self.buf_idx + usize::from(self.num_items) < self.buf_len {
+        // The alternate condition below is faster at string boundaries,
+        // but produces subpar "unsafe-to-concat" values.
+        let mut stop = self.buf_len as isize - self.num_items as isize;
+        if self.ctx.buffer.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT) {
+            stop = self.buf_len as isize - 1;
+        }
+
+        while (self.buf_idx as isize) < stop {
             self.buf_idx += 1;
             let info = &self.ctx.buffer.info[self.buf_idx];
 
@@ -240,16 +254,32 @@ impl<'a, 'b> SkippyIter<'a, 'b> {
             }
 
             if skip == Some(false) {
+                if let Some(unsafe_to) = unsafe_to {
+                    *unsafe_to = self.buf_idx + 1;
+                }
+
                 return false;
             }
         }
 
+        if let Some(unsafe_to) = unsafe_to {
+            *unsafe_to = self.buf_len;
+        }
+
         false
     }
 
-    pub fn prev(&mut self) -> bool {
+    /// On failure, `unsafe_from` is set to the start of the range that is unsafe to concat.
+    pub fn prev(&mut self, unsafe_from: Option<&mut usize>) -> bool {
         assert!(self.num_items > 0);
-        while self.buf_idx >= usize::from(self.num_items) {
+        // The alternate condition below is faster at string boundaries,
+        // but produces subpar "unsafe-to-concat" values.
+        let mut stop = usize::from(self.num_items) - 1;
+        if self.ctx.buffer.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT) {
+            stop = 0;
+        }
+
+        while self.buf_idx > stop {
             self.buf_idx -= 1;
             let info = &self.ctx.buffer.out_info()[self.buf_idx];
 
@@ -265,10 +295,18 @@ impl<'a, 'b> SkippyIter<'a, 'b> {
             }
 
             if skip == Some(false) {
+                if let Some(unsafe_from) = unsafe_from {
+                    *unsafe_from = self.buf_idx.max(1) - 1;
+                }
+
                 return false;
             }
         }
 
+        if let Some(unsafe_from) = unsafe_from {
+            *unsafe_from = 0;
+        }
+
         false
     }
 
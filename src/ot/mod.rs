@@ -1,23 +1,31 @@
 pub mod feature;
 mod apply;
+mod closure;
+mod collect;
 mod contextual;
 mod kerning;
 mod layout;
+mod layout_info;
 mod map;
 pub mod matching;
 mod position;
 mod substitute;
 
 pub use apply::*;
+pub use closure::*;
+pub use collect::{LookupGlyphs, LookupSet};
+pub(crate) use collect::{collect_glyphs, collect_lookups};
 pub use kerning::*;
 pub use layout::*;
+pub use layout_info::{FeatureNames, Layout};
 pub use map::*;
 pub use position::*;
 pub use substitute::*;
 
 use alloc::vec::Vec;
 
-use ttf_parser::opentype_layout::{Coverage, Lookup};
+use ttf_parser::{FromData, GlyphId};
+use ttf_parser::opentype_layout::{Coverage, Lookup, LookupIndex};
 use ttf_parser::gpos::PositioningSubtable;
 use ttf_parser::gsub::SubstitutionSubtable;
 
@@ -42,9 +50,15 @@ pub struct PositioningTable<'a> {
 }
 
 impl<'a> PositioningTable<'a> {
-    pub fn new(inner: ttf_parser::opentype_layout::LayoutTable<'a>) -> Self {
+    pub fn new(inner: ttf_parser::opentype_layout::LayoutTable<'a>, data: Option<&'a [u8]>) -> Self {
         let lookups = inner.lookups.into_iter()
-            .map(PositioningLookup::parse)
+            .enumerate()
+            .map(|(i, lookup)| {
+                let subtables_data = data
+                    .and_then(|data| gpos_subtables_data(data, i as LookupIndex))
+                    .unwrap_or_default();
+                PositioningLookup::parse(lookup, subtables_data)
+            })
             .collect();
 
         Self { inner, lookups}
@@ -53,6 +67,8 @@ impl<'a> PositioningTable<'a> {
 
 pub trait CoverageExt {
     fn collect(&self, set: &mut GlyphSetBuilder);
+    fn intersects(&self, set: &GlyphSet) -> bool;
+    fn intersected(&self, set: &GlyphSet) -> Vec<(u16, GlyphId)>;
 }
 
 impl CoverageExt for Coverage<'_> {
@@ -71,17 +87,59 @@ impl CoverageExt for Coverage<'_> {
             }
         }
     }
+
+    /// Check whether any glyph of this coverage table is in the glyph set.
+    fn intersects(&self, set: &GlyphSet) -> bool {
+        match *self {
+            Self::Format1 { glyphs } => glyphs.into_iter().any(|glyph| set.contains(glyph)),
+            Self::Format2 { records } => records.into_iter().any(|record| {
+                (record.start.0..=record.end.0).any(|glyph| set.contains(GlyphId(glyph)))
+            }),
+        }
+    }
+
+    /// Collect the coverage indices and glyphs of this coverage table
+    /// that are in the glyph set.
+    fn intersected(&self, set: &GlyphSet) -> Vec<(u16, GlyphId)> {
+        let mut result = Vec::new();
+        match *self {
+            Self::Format1 { glyphs } => {
+                for (index, glyph) in glyphs.into_iter().enumerate() {
+                    if set.contains(glyph) {
+                        result.push((index as u16, glyph));
+                    }
+                }
+            }
+            Self::Format2 { records } => {
+                for record in records {
+                    for glyph in record.start.0..=record.end.0 {
+                        if set.contains(GlyphId(glyph)) {
+                            let index = record.value.wrapping_add(glyph - record.start.0);
+                            result.push((index, GlyphId(glyph)));
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
 }
 
 #[derive(Clone)]
 pub struct PositioningLookup<'a> {
     pub subtables: Vec<PositioningSubtable<'a>>,
+    /// Raw data of the subtables, with extension subtables resolved.
+    ///
+    /// `ttf-parser` doesn't expose pair sets and format 2 anchors,
+    /// so we have to read them ourselves.
+    pub subtables_data: Vec<&'a [u8]>,
     pub coverage: GlyphSet,
     pub props: u32,
 }
 
 impl<'a> PositioningLookup<'a> {
-    pub fn parse(lookup: Lookup<'a>) -> Self {
+    pub fn parse(lookup: Lookup<'a>, subtables_data: Vec<&'a [u8]>) -> Self {
         let subtables: Vec<_> = lookup
             .subtables
             .into_iter::<PositioningSubtable>()
@@ -94,6 +152,7 @@ impl<'a> PositioningLookup<'a> {
 
         Self {
             subtables,
+            subtables_data,
             coverage: coverage.finish(),
             props: lookup_props(lookup),
         }
@@ -159,3 +218,26 @@ fn lookup_props(lookup: Lookup) -> u32 {
     }
     props
 }
+
+/// Returns the raw data of `GPOS` lookup subtables, with extension subtables resolved.
+fn gpos_subtables_data(data: &[u8], lookup_index: LookupIndex) -> Option<Vec<&[u8]>> {
+    let read = |offset: usize| data.get(offset..offset + 2).and_then(u16::parse).map(usize::from);
+
+    let lookup_list = read(8)?;
+    let lookup = lookup_list + read(lookup_list + 2 + usize::from(lookup_index) * 2)?;
+    let kind = read(lookup)?;
+    let count = read(lookup + 4)?;
+
+    let mut subtables = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut subtable = lookup + read(lookup + 6 + i * 2)?;
+        // https://docs.microsoft.com/en-us/typography/opentype/spec/gpos#lookuptype-9-extension-positioning
+        if kind == 9 {
+            let offset = data.get(subtable + 4..subtable + 8).and_then(u32::parse)?;
+            subtable += offset as usize;
+        }
+        subtables.push(data.get(subtable..)?);
+    }
+
+    Some(subtables)
+}
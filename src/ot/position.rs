@@ -1,4 +1,6 @@
-use ttf_parser::GlyphId;
+use core::convert::TryFrom;
+
+use ttf_parser::{FromData, GlyphId};
 use ttf_parser::opentype_layout::LookupIndex;
 use ttf_parser::gpos::*;
 
@@ -24,7 +26,7 @@ pub fn position(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
 
 pub fn position_finish_advances(_: &Face, _: &mut Buffer) {}
 
-pub fn position_finish_offsets(_: &Face, buffer: &mut Buffer) {
+pub fn position_finish_offsets(face: &Face, buffer: &mut Buffer) {
     let len = buffer.len;
     let direction = buffer.direction;
 
@@ -34,6 +36,14 @@ pub fn position_finish_offsets(_: &Face, buffer: &mut Buffer) {
             propagate_attachment_offsets(&mut buffer.pos, len, i, direction);
         }
     }
+
+    if face.slant_xy != 0.0 {
+        for pos in &mut buffer.pos[..len] {
+            if pos.y_offset != 0 {
+                pos.x_offset += crate::round(face.slant_xy * pos.y_offset as f32) as i32;
+            }
+        }
+    }
 }
 
 fn propagate_attachment_offsets(
@@ -116,8 +126,8 @@ impl LayoutLookup for PositioningLookup<'_> {
 impl Apply for PositioningLookup<'_> {
     fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
         if self.covers(ctx.buffer.cur(0).as_glyph()) {
-            for subtable in &self.subtables {
-                if subtable.apply(ctx).is_some() {
+            for (i, subtable) in self.subtables.iter().enumerate() {
+                if subtable.apply(ctx, self.subtables_data.get(i).copied()).is_some() {
                     return Some(());
                 }
             }
@@ -127,15 +137,20 @@ impl Apply for PositioningLookup<'_> {
     }
 }
 
-impl Apply for PositioningSubtable<'_> {
-    fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
+/// Apply a subtable that reads its raw data.
+trait ApplyWithData {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()>;
+}
+
+impl ApplyWithData for PositioningSubtable<'_> {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()> {
         match self {
             Self::Single(t) => t.apply(ctx),
             Self::Pair(t) => t.apply(ctx),
-            Self::Cursive(t) => t.apply(ctx),
-            Self::MarkToBase(t) => t.apply(ctx),
-            Self::MarkToLigature(t) => t.apply(ctx),
-            Self::MarkToMark(t) => t.apply(ctx),
+            Self::Cursive(t) => t.apply(ctx, data),
+            Self::MarkToBase(t) => t.apply(ctx, data),
+            Self::MarkToLigature(t) => t.apply(ctx, data),
+            Self::MarkToMark(t) => t.apply(ctx, data),
             Self::Context(t) => t.apply(ctx),
             Self::ChainContext(t) => t.apply(ctx),
         }
@@ -167,7 +182,9 @@ impl Apply for PairAdjustment<'_> {
         let index = self.coverage().get(first)?;
 
         let mut iter = SkippyIter::new(ctx, ctx.buffer.idx, 1, false);
-        if !iter.next() {
+        let mut unsafe_to = 0;
+        if !iter.next(Some(&mut unsafe_to)) {
+            ctx.buffer.unsafe_to_concat(ctx.buffer.idx, unsafe_to);
             return None;
         }
 
@@ -176,19 +193,29 @@ impl Apply for PairAdjustment<'_> {
 
         let records = match self {
             Self::Format1 { sets, .. } => {
-                sets.get(index)?.get(second)
+                sets.get(index).and_then(|set| set.get(second))
             }
             Self::Format2 { classes, matrix, .. } => {
                 let classes = (classes.0.get(first), classes.1.get(second));
                 matrix.get(classes)
             }
-        }?;
+        };
+
+        let records = match records {
+            Some(records) => records,
+            None => {
+                ctx.buffer.unsafe_to_concat(ctx.buffer.idx, pos + 1);
+                return None;
+            }
+        };
 
         let flag1 = records.0.apply(ctx, ctx.buffer.idx);
         let flag2 = records.1.apply(ctx, pos);
         // Note the intentional use of "|" instead of short-circuit "||".
         if flag1 | flag2 {
             ctx.buffer.unsafe_to_break(ctx.buffer.idx, pos + 1);
+        } else {
+            ctx.buffer.unsafe_to_concat(ctx.buffer.idx, pos + 1);
         }
 
         ctx.buffer.idx = pos + usize::from(flag2);
@@ -196,55 +223,66 @@ impl Apply for PairAdjustment<'_> {
     }
 }
 
-impl Apply for CursiveAdjustment<'_> {
-    fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
+impl ApplyWithData for CursiveAdjustment<'_> {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()> {
         let this = ctx.buffer.cur(0).as_glyph();
 
         let index_this = self.coverage.get(this)?;
         let entry_this = self.sets.entry(index_this)?;
 
         let mut iter = SkippyIter::new(ctx, ctx.buffer.idx, 1, false);
-        if !iter.prev() {
+        let mut unsafe_from = 0;
+        if !iter.prev(Some(&mut unsafe_from)) {
+            ctx.buffer.unsafe_to_concat_from_outbuffer(unsafe_from, ctx.buffer.idx + 1);
             return None;
         }
 
         let i = iter.index();
         let prev = ctx.buffer.info[i].as_glyph();
-        let index_prev = self.coverage.get(prev)?;
-        let exit_prev = self.sets.exit(index_prev)?;
+        let index_prev = self.coverage.get(prev);
+        let exit_prev = match index_prev.and_then(|index| self.sets.exit(index)) {
+            Some(exit_prev) => exit_prev,
+            None => {
+                ctx.buffer.unsafe_to_concat_from_outbuffer(i, ctx.buffer.idx + 1);
+                return None;
+            }
+        };
 
-        let (exit_x, exit_y) = exit_prev.get(ctx.face);
-        let (entry_x, entry_y) = entry_this.get(ctx.face);
+        let exit_data = data.zip(index_prev).and_then(|(data, index)| cursive_anchor_data(data, index, true));
+        let entry_data = data.and_then(|data| cursive_anchor_data(data, index_this, false));
+        let (exit_x, exit_y) = exit_prev.get(ctx.face, prev, exit_data);
+        let (entry_x, entry_y) = entry_this.get(ctx.face, this, entry_data);
 
         let direction = ctx.buffer.direction;
         let j = ctx.buffer.idx;
         ctx.buffer.unsafe_to_break(i, j);
 
+        let round = |v: f32| crate::round(v) as i32;
         let pos = &mut ctx.buffer.pos;
         match direction {
             Direction::LeftToRight => {
-                pos[i].x_advance = exit_x + pos[i].x_offset;
-                let d = entry_x + pos[j].x_offset;
+                pos[i].x_advance = round(exit_x) + pos[i].x_offset;
+                let d = round(entry_x) + pos[j].x_offset;
                 pos[j].x_advance -= d;
                 pos[j].x_offset -= d;
             }
             Direction::RightToLeft => {
-                let d = exit_x + pos[i].x_offset;
+                let d = round(exit_x) + pos[i].x_offset;
                 pos[i].x_advance -= d;
                 pos[i].x_offset -= d;
-                pos[j].x_advance = entry_x + pos[j].x_offset;
+                pos[j].x_advance = round(entry_x) + pos[j].x_offset;
             }
             Direction::TopToBottom => {
-                pos[i].y_advance = exit_y + pos[i].y_offset;
-                let d = entry_y + pos[j].y_offset;
+                pos[i].y_advance = round(exit_y) + pos[i].y_offset;
+                let d = round(entry_y) + pos[j].y_offset;
                 pos[j].y_advance -= d;
                 pos[j].y_offset -= d;
             }
             Direction::BottomToTop => {
-                let d = exit_y + pos[i].y_offset;
+                let d = round(exit_y) + pos[i].y_offset;
                 pos[i].y_advance -= d;
                 pos[i].y_offset -= d;
-                pos[j].y_advance = entry_y;
+                pos[j].y_advance = round(entry_y);
             }
             Direction::Invalid => {}
         }
@@ -259,8 +297,8 @@ impl Apply for CursiveAdjustment<'_> {
         // Arabic.
         let mut child = i;
         let mut parent = j;
-        let mut x_offset = entry_x - exit_x;
-        let mut y_offset = entry_y - exit_y;
+        let mut x_offset = round(entry_x - exit_x);
+        let mut y_offset = round(entry_y - exit_y);
 
         // Low bits are lookup flags, so we want to truncate.
         if ctx.lookup_props as u16 & lookup_flags::RIGHT_TO_LEFT == 0 {
@@ -328,8 +366,8 @@ fn reverse_cursive_minor_offset(
     pos[j].set_attach_type(attach_type);
 }
 
-impl Apply for MarkToBaseAdjustment<'_> {
-    fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
+impl ApplyWithData for MarkToBaseAdjustment<'_> {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()> {
         let buffer = &ctx.buffer;
         let mark_glyph = ctx.buffer.cur(0).as_glyph();
         let mark_index = self.mark_coverage.get(mark_glyph)?;
@@ -340,7 +378,9 @@ impl Apply for MarkToBaseAdjustment<'_> {
 
         let info = &buffer.info;
         loop {
-            if !iter.prev() {
+            let mut unsafe_from = 0;
+            if !iter.prev(Some(&mut unsafe_from)) {
+                ctx.buffer.unsafe_to_concat_from_outbuffer(unsafe_from, ctx.buffer.idx + 1);
                 return None;
             }
 
@@ -366,14 +406,21 @@ impl Apply for MarkToBaseAdjustment<'_> {
 
         let idx = iter.index();
         let base_glyph = info[idx].as_glyph();
-        let base_index = self.base_coverage.get(base_glyph)?;
+        let base_index = match self.base_coverage.get(base_glyph) {
+            Some(base_index) => base_index,
+            None => {
+                ctx.buffer.unsafe_to_concat_from_outbuffer(idx, ctx.buffer.idx + 1);
+                return None;
+            }
+        };
 
-        self.marks.apply(ctx, self.anchors, mark_index, base_index, idx)
+        let data = data.and_then(|data| MarkData::new(data, None));
+        self.marks.apply(ctx, data, self.anchors, mark_index, base_index, idx)
     }
 }
 
-impl Apply for MarkToLigatureAdjustment<'_> {
-    fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
+impl ApplyWithData for MarkToLigatureAdjustment<'_> {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()> {
         let buffer = &ctx.buffer;
         let mark_glyph = ctx.buffer.cur(0).as_glyph();
         let mark_index = self.mark_coverage.get(mark_glyph)?;
@@ -381,7 +428,9 @@ impl Apply for MarkToLigatureAdjustment<'_> {
         // Now we search backwards for a non-mark glyph
         let mut iter = SkippyIter::new(ctx, buffer.idx, 1, false);
         iter.set_lookup_props(u32::from(lookup_flags::IGNORE_MARKS));
-        if !iter.prev() {
+        let mut unsafe_from = 0;
+        if !iter.prev(Some(&mut unsafe_from)) {
+            ctx.buffer.unsafe_to_concat_from_outbuffer(unsafe_from, ctx.buffer.idx + 1);
             return None;
         }
 
@@ -389,14 +438,20 @@ impl Apply for MarkToLigatureAdjustment<'_> {
 
         let idx = iter.index();
         let lig_glyph = buffer.info[idx].as_glyph();
-        let lig_index = self.ligature_coverage.get(lig_glyph)?;
-        let lig_attach = self.ligature_array.get(lig_index)?;
+        let lig_index = self.ligature_coverage.get(lig_glyph);
+        let lig_attach = lig_index
+            .and_then(|lig_index| self.ligature_array.get(lig_index))
+            .filter(|lig_attach| lig_attach.rows != 0);
+        let lig_attach = match lig_attach {
+            Some(lig_attach) => lig_attach,
+            None => {
+                ctx.buffer.unsafe_to_concat_from_outbuffer(idx, ctx.buffer.idx + 1);
+                return None;
+            }
+        };
 
         // Find component to attach to
         let comp_count = lig_attach.rows;
-        if comp_count == 0 {
-            return None;
-        }
 
         // We must now check whether the ligature ID of the current mark glyph
         // is identical to the ligature ID of the found ligature.  If yes, we
@@ -408,12 +463,13 @@ impl Apply for MarkToLigatureAdjustment<'_> {
         let matches = lig_id != 0 && lig_id == mark_id && mark_comp > 0;
         let comp_index = if matches { mark_comp.min(comp_count) } else { comp_count } - 1;
 
-        self.marks.apply(ctx, lig_attach, mark_index, comp_index, idx)
+        let data = data.and_then(|data| MarkData::new(data, lig_index));
+        self.marks.apply(ctx, data, lig_attach, mark_index, comp_index, idx)
     }
 }
 
-impl Apply for MarkToMarkAdjustment<'_> {
-    fn apply(&self, ctx: &mut ApplyContext) -> Option<()> {
+impl ApplyWithData for MarkToMarkAdjustment<'_> {
+    fn apply(&self, ctx: &mut ApplyContext, data: Option<&[u8]>) -> Option<()> {
         let buffer = &ctx.buffer;
         let mark1_glyph = ctx.buffer.cur(0).as_glyph();
         let mark1_index = self.mark1_coverage.get(mark1_glyph)?;
@@ -421,12 +477,15 @@ impl Apply for MarkToMarkAdjustment<'_> {
         // Now we search backwards for a suitable mark glyph until a non-mark glyph
         let mut iter = SkippyIter::new(ctx, buffer.idx, 1, false);
         iter.set_lookup_props(ctx.lookup_props & !u32::from(lookup_flags::IGNORE_FLAGS));
-        if !iter.prev() {
+        let mut unsafe_from = 0;
+        if !iter.prev(Some(&mut unsafe_from)) {
+            ctx.buffer.unsafe_to_concat_from_outbuffer(unsafe_from, ctx.buffer.idx + 1);
             return None;
         }
 
         let idx = iter.index();
         if !buffer.info[idx].is_mark() {
+            ctx.buffer.unsafe_to_concat_from_outbuffer(idx, ctx.buffer.idx + 1);
             return None;
         }
 
@@ -445,14 +504,17 @@ impl Apply for MarkToMarkAdjustment<'_> {
             (id1 > 0 && comp1 == 0) || (id2 > 0 && comp2 == 0)
         };
 
-        if !matches {
-            return None;
-        }
-
         let mark2_glyph = buffer.info[idx].as_glyph();
-        let mark2_index = self.mark2_coverage.get(mark2_glyph)?;
+        let mark2_index = match self.mark2_coverage.get(mark2_glyph).filter(|_| matches) {
+            Some(mark2_index) => mark2_index,
+            None => {
+                ctx.buffer.unsafe_to_concat(idx, ctx.buffer.idx + 1);
+                return None;
+            }
+        };
 
-        self.marks.apply(ctx, self.mark2_matrix, mark1_index, mark2_index, idx)
+        let data = data.and_then(|data| MarkData::new(data, None));
+        self.marks.apply(ctx, data, self.mark2_matrix, mark1_index, mark2_index, idx)
     }
 }
 
@@ -467,23 +529,23 @@ impl ValueRecordExt for ValueRecord<'_> {
         let mut worked = false;
 
         if self.x_placement != 0 {
-            pos.x_offset += i32::from(self.x_placement);
+            pos.x_offset += ctx.face.em_scale_x(i32::from(self.x_placement));
             worked = true;
         }
 
         if self.y_placement != 0 {
-            pos.y_offset += i32::from(self.y_placement);
+            pos.y_offset += ctx.face.em_scale_y(i32::from(self.y_placement));
             worked = true;
         }
 
         if self.x_advance != 0 && horizontal {
-            pos.x_advance += i32::from(self.x_advance);
+            pos.x_advance += ctx.face.em_scale_x(i32::from(self.x_advance));
             worked = true;
         }
 
         if self.y_advance != 0 && !horizontal {
             // y_advance values grow downward but font-space grows upward, hence negation
-            pos.y_advance -= i32::from(self.y_advance);
+            pos.y_advance -= ctx.face.em_scale_y(i32::from(self.y_advance));
             worked = true;
         }
 
@@ -532,6 +594,7 @@ trait MarkArrayExt {
     fn apply(
         &self,
         ctx: &mut ApplyContext,
+        data: Option<MarkData>,
         anchors: AnchorMatrix,
         mark_index: u16,
         glyph_index: u16,
@@ -543,6 +606,7 @@ impl MarkArrayExt for MarkArray<'_> {
     fn apply(
         &self,
         ctx: &mut ApplyContext,
+        data: Option<MarkData>,
         anchors: AnchorMatrix,
         mark_index: u16,
         glyph_index: u16,
@@ -553,15 +617,17 @@ impl MarkArrayExt for MarkArray<'_> {
         let (mark_class, mark_anchor) = self.get(mark_index)?;
         let base_anchor = anchors.get(glyph_index, mark_class)?;
 
-        let (mark_x, mark_y) = mark_anchor.get(ctx.face);
-        let (base_x, base_y) = base_anchor.get(ctx.face);
+        let mark_data = data.and_then(|data| data.mark_anchor(mark_index));
+        let base_data = data.and_then(|data| data.anchor(glyph_index, mark_class, anchors.cols));
+        let (mark_x, mark_y) = mark_anchor.get(ctx.face, ctx.buffer.cur(0).as_glyph(), mark_data);
+        let (base_x, base_y) = base_anchor.get(ctx.face, ctx.buffer.info[glyph_pos].as_glyph(), base_data);
 
         ctx.buffer.unsafe_to_break(glyph_pos, ctx.buffer.idx);
 
         let idx = ctx.buffer.idx;
         let pos = ctx.buffer.cur_pos_mut();
-        pos.x_offset = base_x - mark_x;
-        pos.y_offset = base_y - mark_y;
+        pos.x_offset = crate::round(base_x - mark_x) as i32;
+        pos.y_offset = crate::round(base_y - mark_y) as i32;
         pos.set_attach_type(attach_type::MARK);
         pos.set_attach_chain((glyph_pos as isize - idx as isize) as i16);
 
@@ -572,6 +638,69 @@ impl MarkArrayExt for MarkArray<'_> {
     }
 }
 
+/// Returns the outline point index of a format 2 anchor.
+///
+/// `ttf-parser` doesn't expose it, so we have to read it ourselves.
+fn anchor_point(data: &[u8]) -> Option<u16> {
+    match read_u16(data, 0)? {
+        2 => read_u16(data, 6),
+        _ => None,
+    }
+}
+
+/// Returns the raw data of a cursive attachment entry or exit anchor.
+fn cursive_anchor_data(data: &[u8], index: u16, exit: bool) -> Option<&[u8]> {
+    let offset = read_u16(data, 6 + usize::from(index) * 4 + usize::from(exit) * 2)?;
+    // Zero offsets must be ignored.
+    if offset == 0 {
+        return None;
+    }
+
+    data.get(usize::from(offset)..)
+}
+
+/// Raw data of a mark attachment subtable.
+#[derive(Clone, Copy)]
+struct MarkData<'a> {
+    marks: &'a [u8],
+    anchors: &'a [u8],
+}
+
+impl<'a> MarkData<'a> {
+    /// Mark-to-ligature subtables store an anchor matrix per ligature,
+    /// which is selected by `lig_index`.
+    fn new(data: &'a [u8], lig_index: Option<u16>) -> Option<Self> {
+        let marks = data.get(usize::from(read_u16(data, 8)?)..)?;
+        let mut anchors = data.get(usize::from(read_u16(data, 10)?)..)?;
+        if let Some(index) = lig_index {
+            let offset = read_u16(anchors, 2 + usize::from(index) * 2)?;
+            anchors = anchors.get(usize::from(offset)..)?;
+        }
+
+        Some(MarkData { marks, anchors })
+    }
+
+    fn mark_anchor(&self, index: u16) -> Option<&'a [u8]> {
+        let offset = read_u16(self.marks, 2 + usize::from(index) * 4 + 2)?;
+        self.marks.get(usize::from(offset)..)
+    }
+
+    fn anchor(&self, row: u16, col: u16, cols: u16) -> Option<&'a [u8]> {
+        let index = usize::from(row) * usize::from(cols) + usize::from(col);
+        let offset = read_u16(self.anchors, 2 + index * 2)?;
+        // Zero offsets must be ignored.
+        if offset == 0 {
+            return None;
+        }
+
+        self.anchors.get(usize::from(offset)..)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).and_then(u16::parse)
+}
+
 pub mod attach_type {
     pub const MARK: u8 = 1;
     pub const CURSIVE: u8 = 2;
@@ -613,36 +742,64 @@ trait DeviceExt {
 impl DeviceExt for Device<'_> {
     fn get_x_delta(&self, face: &Face) -> Option<i32> {
         match self {
-            Device::Hinting(hinting) => hinting.x_delta(face.units_per_em, face.pixels_per_em()),
+            Device::Hinting(hinting) => {
+                let (ppem, _) = face.pixels_per_em()?;
+                // Using ppem as a scale gives us a delta in pixels.
+                let pixels = hinting.x_delta(ppem, Some((ppem, ppem)))?;
+                scale_pixels(pixels, face.scale().0, ppem)
+            }
             Device::Variation(variation) => {
                 face.tables().gdef?
                     .glyph_variation_delta(variation.outer_index, variation.inner_index, face.variation_coordinates())
-                    .and_then(|float| i32::try_num_from(crate::round(float)))
+                    .and_then(|float| i32::try_num_from(crate::round(face.em_fscale_x(float))))
             }
         }
     }
 
     fn get_y_delta(&self, face: &Face) -> Option<i32> {
         match self {
-            Device::Hinting(hinting) => hinting.y_delta(face.units_per_em, face.pixels_per_em()),
+            Device::Hinting(hinting) => {
+                let (_, ppem) = face.pixels_per_em()?;
+                let pixels = hinting.y_delta(ppem, Some((ppem, ppem)))?;
+                scale_pixels(pixels, face.scale().1, ppem)
+            }
             Device::Variation(variation) => {
                 face.tables().gdef?
                     .glyph_variation_delta(variation.outer_index, variation.inner_index, face.variation_coordinates())
-                    .and_then(|float| i32::try_num_from(crate::round(float)))
+                    .and_then(|float| i32::try_num_from(crate::round(face.em_fscale_y(float))))
             }
         }
     }
 }
 
+fn scale_pixels(pixels: i32, scale: i32, ppem: u16) -> Option<i32> {
+    i32::try_from(i64::from(pixels) * i64::from(scale) / i64::from(ppem)).ok()
+}
+
 
 trait AnchorExt {
-    fn get(&self, face: &Face) -> (i32, i32);
+    fn get(&self, face: &Face, glyph: GlyphId, data: Option<&[u8]>) -> (f32, f32);
 }
 
 impl AnchorExt for Anchor<'_> {
-    fn get(&self, face: &Face) -> (i32, i32) {
-        let mut x = i32::from(self.x);
-        let mut y = i32::from(self.y);
+    fn get(&self, face: &Face, glyph: GlyphId, data: Option<&[u8]>) -> (f32, f32) {
+        let mut x = face.em_fscale_x(f32::from(self.x));
+        let mut y = face.em_fscale_y(f32::from(self.y));
+
+        // Format 2 anchors are placed on an outline point, but only when hinting.
+        if let Some((ppem_x, ppem_y)) = face.pixels_per_em() {
+            let point = data.and_then(anchor_point)
+                .and_then(|point| face.glyph_contour_point(glyph, point));
+            if let Some((point_x, point_y)) = point {
+                let (origin_x, origin_y) = face.glyph_h_origin(glyph);
+                if ppem_x != 0 {
+                    x = (point_x - origin_x) as f32;
+                }
+                if ppem_y != 0 {
+                    y = (point_y - origin_y) as f32;
+                }
+            }
+        }
 
         if self.x_device.is_some() || self.y_device.is_some() {
             let (ppem_x, ppem_y) = face.pixels_per_em().unwrap_or((0, 0));
@@ -650,13 +807,13 @@ impl AnchorExt for Anchor<'_> {
 
             if let Some(device) = self.x_device {
                 if ppem_x != 0 || coords != 0 {
-                    x += device.get_x_delta(face).unwrap_or(0);
+                    x += device.get_x_delta(face).unwrap_or(0) as f32;
                 }
             }
 
             if let Some(device) = self.y_device {
                 if ppem_y != 0 || coords != 0 {
-                    y += device.get_y_delta(face).unwrap_or(0);
+                    y += device.get_y_delta(face).unwrap_or(0) as f32;
                 }
             }
         }
@@ -1,14 +1,15 @@
+use alloc::vec::Vec;
 use core::convert::TryFrom;
 
 use ttf_parser::GlyphId;
 use ttf_parser::gsub::*;
 
-use crate::Face;
+use crate::{Face, Language, Script, Tag};
 use crate::buffer::{Buffer, GlyphPropsFlags};
 use crate::plan::ShapePlan;
 use crate::unicode::GeneralCategory;
 
-use super::{Map, LayoutLookup, LayoutTable, TableIndex, SubstitutionTable, SubstLookup, MAX_NESTING_LEVEL};
+use super::{FeatureFlags, Map, MapBuilder, LayoutLookup, LayoutTable, TableIndex, SubstitutionTable, SubstLookup, MAX_NESTING_LEVEL};
 use super::apply::{Apply, ApplyContext, WouldApply, WouldApplyContext};
 use super::matching::{
     match_backtrack, match_glyph, match_input, match_lookahead, Matched,
@@ -25,6 +26,86 @@ pub fn substitute(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
     super::apply_layout_table(plan, face, buffer, face.gsub.as_ref());
 }
 
+/// Checks whether the lookups of a feature would substitute the glyph sequence.
+///
+/// Resolves the feature lookups the same way shaping does.
+pub fn would_substitute(
+    face: &Face,
+    script: Option<Script>,
+    language: Option<&Language>,
+    feature_tag: Tag,
+    glyphs: &[GlyphId],
+    zero_context: bool,
+) -> bool {
+    let table = match face.gsub.as_ref() {
+        Some(table) if !glyphs.is_empty() => table,
+        _ => return false,
+    };
+
+    let ctx = WouldApplyContext { glyphs, zero_context };
+    feature_lookups(face, script, language, feature_tag)
+        .into_iter()
+        .filter_map(|index| table.get_lookup(index))
+        .any(|lookup| lookup.would_apply(&ctx))
+}
+
+/// Returns alternates of a glyph for a feature.
+///
+/// Resolves the feature lookups the same way shaping does.
+pub fn glyph_alternates(
+    face: &Face,
+    script: Option<Script>,
+    language: Option<&Language>,
+    feature_tag: Tag,
+    glyph: GlyphId,
+) -> Vec<GlyphId> {
+    let mut alternates = Vec::new();
+    let table = match face.gsub.as_ref() {
+        Some(table) => table,
+        None => return alternates,
+    };
+
+    for index in feature_lookups(face, script, language, feature_tag) {
+        if let Some(lookup) = table.get_lookup(index) {
+            lookup.collect_alternates(glyph, &mut alternates);
+        }
+    }
+
+    let mut unique = Vec::with_capacity(alternates.len());
+    for alternate in alternates {
+        if alternate != glyph && !unique.contains(&alternate) {
+            unique.push(alternate);
+        }
+    }
+
+    unique
+}
+
+/// Returns `GSUB` lookups of a feature in application order.
+fn feature_lookups(
+    face: &Face,
+    script: Option<Script>,
+    language: Option<&Language>,
+    feature_tag: Tag,
+) -> Vec<LookupIndex> {
+    let mut builder = MapBuilder::new(face, script, language);
+    // A non-global feature gets its own mask,
+    // so its lookups can be told apart from the required feature ones.
+    builder.add_feature(feature_tag, FeatureFlags::empty(), 1);
+    let map = builder.compile();
+
+    let mask = map.mask(feature_tag).0;
+    if mask == 0 {
+        return Vec::new();
+    }
+
+    map.stages(TableIndex::GSUB).iter().enumerate()
+        .flat_map(|(stage, _)| map.stage_lookups(TableIndex::GSUB, stage))
+        .filter(|lookup| lookup.mask & mask != 0)
+        .map(|lookup| lookup.index)
+        .collect()
+}
+
 fn set_glyph_props(face: &Face, buffer: &mut Buffer) {
     let len = buffer.len;
     for info in &mut buffer.info[..len] {
@@ -108,6 +189,37 @@ impl Apply for SubstitutionSubtable<'_> {
     }
 }
 
+impl SubstLookup<'_> {
+    /// Collects glyphs that single and alternate substitutions
+    /// of this lookup can replace the glyph with.
+    pub fn collect_alternates(&self, glyph: GlyphId, alternates: &mut Vec<GlyphId>) {
+        for subtable in &self.subtables {
+            match *subtable {
+                SubstitutionSubtable::Single(ref single) => {
+                    let subst = match *single {
+                        SingleSubstitution::Format1 { coverage, delta } => {
+                            coverage.get(glyph).map(|_| GlyphId((i32::from(glyph.0) + i32::from(delta)) as u16))
+                        }
+                        SingleSubstitution::Format2 { coverage, substitutes } => {
+                            coverage.get(glyph).and_then(|index| substitutes.get(index))
+                        }
+                    };
+
+                    alternates.extend(subst);
+                }
+                SubstitutionSubtable::Alternate(ref alternate) => {
+                    if let Some(set) = alternate.coverage.get(glyph)
+                        .and_then(|index| alternate.alternate_sets.get(index))
+                    {
+                        alternates.extend(set.alternates);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 impl WouldApply for SingleSubstitution<'_> {
     fn would_apply(&self, ctx: &WouldApplyContext) -> bool {
         ctx.glyphs.len() == 1 && self.coverage().get(ctx.glyphs[0]).is_some()
@@ -279,10 +391,17 @@ impl Apply for Ligature<'_> {
                 match_glyph(glyph, value.0)
             };
 
-            match_input(ctx, self.components.len(), &f).map(|matched| {
-                let count = usize::from(self.components.len()) + 1;
-                ligate(ctx, count, matched, self.glyph);
-            })
+            match match_input(ctx, self.components.len(), &f) {
+                Ok(matched) => {
+                    let count = usize::from(self.components.len()) + 1;
+                    ligate(ctx, count, matched, self.glyph);
+                    Some(())
+                }
+                Err(end) => {
+                    ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+                    None
+                }
+            }
         }
     }
 }
@@ -425,18 +544,28 @@ impl Apply for ReverseChainSingleSubstitution<'_> {
             value.contains(glyph)
         };
 
-        if let Some(start_idx) = match_backtrack(ctx, self.backtrack_coverages.len(), &f1) {
-            if let Some(end_idx) = match_lookahead(ctx, self.lookahead_coverages.len(), &f2, 1) {
-                ctx.buffer.unsafe_to_break_from_outbuffer(start_idx, end_idx);
-                ctx.replace_glyph_inplace(subst);
+        let end_idx = match match_lookahead(ctx, self.lookahead_coverages.len(), &f2, 1) {
+            Ok(end_idx) => end_idx,
+            Err(end) => {
+                ctx.buffer.unsafe_to_concat(ctx.buffer.idx, end);
+                return None;
+            }
+        };
 
-                // Note: We DON'T decrease buffer.idx.  The main loop does it
-                // for us.  This is useful for preventing surprises if someone
-                // calls us through a Context lookup.
-                return Some(());
+        let start_idx = match match_backtrack(ctx, self.backtrack_coverages.len(), &f1) {
+            Ok(start_idx) => start_idx,
+            Err(start) => {
+                ctx.buffer.unsafe_to_concat_from_outbuffer(start, end_idx);
+                return None;
             }
-        }
+        };
 
-        None
+        ctx.buffer.unsafe_to_break_from_outbuffer(start_idx, end_idx);
+        ctx.replace_glyph_inplace(subst);
+
+        // Note: We DON'T decrease buffer.idx.  The main loop does it
+        // for us.  This is useful for preventing surprises if someone
+        // calls us through a Context lookup.
+        Some(())
     }
 }
@@ -1,43 +1,65 @@
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::any::Any;
+use core::marker::PhantomData;
 
-use crate::{aat, Direction, Face, Feature, Language, Mask, Tag, Script};
+use crate::{aat, Direction, Face, Feature, GlyphBuffer, Language, Mask, Tag, Script, UnicodeBuffer};
 use crate::complex::{complex_categorize, ComplexShaper, DEFAULT_SHAPER, DUMBER_SHAPER};
+use crate::face::FaceKey;
 use crate::ot::{self, feature, FeatureFlags, TableIndex};
 
+/// A reusable shaping plan.
+///
+/// A plan holds everything that can be precomputed for a combination of
+/// a face, segment properties and user features.
+/// Creating a plan is relatively expensive, so when the same combination is
+/// shaped repeatedly, the plan can be created once and passed to
+/// [`shape_with_plan`](crate::shape_with_plan).
 pub struct ShapePlan {
-    pub direction: Direction,
-    pub script: Option<Script>,
-    pub shaper: &'static ComplexShaper,
-    pub ot_map: ot::Map,
-    pub aat_map: aat::Map,
+    pub(crate) face_key: FaceKey,
+    pub(crate) direction: Direction,
+    pub(crate) script: Option<Script>,
+    pub(crate) language: Option<Language>,
+    pub(crate) user_features: Vec<Feature>,
+    pub(crate) shaper: &'static ComplexShaper,
+    pub(crate) ot_map: ot::Map,
+    pub(crate) aat_map: aat::Map,
     data: Option<Box<dyn Any>>,
 
-    pub frac_mask: Mask,
-    pub numr_mask: Mask,
-    pub dnom_mask: Mask,
-    pub rtlm_mask: Mask,
-    pub kern_mask: Mask,
-    pub trak_mask: Mask,
-
-    pub requested_kerning: bool,
-    pub requested_tracking: bool,
-    pub has_frac: bool,
-    pub has_vert: bool,
-    pub has_gpos_mark: bool,
-    pub zero_marks: bool,
-    pub fallback_glyph_classes: bool,
-    pub fallback_mark_positioning: bool,
-    pub adjust_mark_positioning_when_zeroing: bool,
-
-    pub apply_gpos: bool,
-    pub apply_kern: bool,
-    pub apply_kerx: bool,
-    pub apply_morx: bool,
-    pub apply_trak: bool,
+    pub(crate) frac_mask: Mask,
+    pub(crate) numr_mask: Mask,
+    pub(crate) dnom_mask: Mask,
+    pub(crate) rtlm_mask: Mask,
+    pub(crate) kern_mask: Mask,
+    pub(crate) trak_mask: Mask,
+
+    pub(crate) requested_kerning: bool,
+    pub(crate) requested_tracking: bool,
+    pub(crate) has_frac: bool,
+    pub(crate) has_vert: bool,
+    pub(crate) has_gpos_mark: bool,
+    pub(crate) zero_marks: bool,
+    pub(crate) fallback_glyph_classes: bool,
+    pub(crate) fallback_mark_positioning: bool,
+    pub(crate) adjust_mark_positioning_when_zeroing: bool,
+
+    pub(crate) apply_gpos: bool,
+    pub(crate) apply_kern: bool,
+    pub(crate) apply_kerx: bool,
+    pub(crate) apply_morx: bool,
+    pub(crate) apply_trak: bool,
 }
 
 impl ShapePlan {
+    /// Creates a new shaping plan.
+    ///
+    /// `direction`, `script` and `language` must match the properties of
+    /// the buffers that will be shaped with this plan.
+    /// See [`UnicodeBuffer::guess_segment_properties`](crate::UnicodeBuffer::guess_segment_properties).
+    ///
+    /// # Panics
+    ///
+    /// Panics when `direction` is `Direction::Invalid`.
     pub fn new(
         face: &Face,
         direction: Direction,
@@ -51,15 +73,245 @@ impl ShapePlan {
         planner.compile()
     }
 
-    pub fn data<T: 'static>(&self) -> &T {
+    /// Returns the plan's direction.
+    #[inline]
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the plan's script.
+    #[inline]
+    pub fn script(&self) -> Option<Script> {
+        self.script
+    }
+
+    /// Returns the plan's language.
+    #[inline]
+    pub fn language(&self) -> Option<&Language> {
+        self.language.as_ref()
+    }
+
+    pub(crate) fn data<T: 'static>(&self) -> &T {
         self.data.as_ref().unwrap().downcast_ref().unwrap()
     }
+
+    /// Checks that the plan can be used to shape a buffer with
+    /// the provided face, properties and features.
+    pub(crate) fn check(
+        &self,
+        face: &Face,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        user_features: &[Feature],
+    ) -> Result<(), ShapePlanError> {
+        if !self.face_key.matches(face) {
+            return Err(ShapePlanError::FaceMismatch);
+        }
+
+        if self.direction != direction {
+            return Err(ShapePlanError::DirectionMismatch);
+        }
+
+        if self.script != script {
+            return Err(ShapePlanError::ScriptMismatch);
+        }
+
+        if self.language.as_ref() != language {
+            return Err(ShapePlanError::LanguageMismatch);
+        }
+
+        if !features_match(&self.user_features, user_features) {
+            return Err(ShapePlanError::FeaturesMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+// Ranges of non-global features are applied per buffer,
+// so only the tag, the value and the globalness affect the plan.
+fn features_match(a: &[Feature], b: &[Feature]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| {
+        a.tag == b.tag && a.value == b.value && a.is_global() == b.is_global()
+    })
+}
+
+/// A shaping plan mismatch error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShapePlanError {
+    /// The plan was created for a different face or different variation coordinates.
+    FaceMismatch,
+    /// The plan was created for a different direction.
+    DirectionMismatch,
+    /// The plan was created for a different script.
+    ScriptMismatch,
+    /// The plan was created for a different language.
+    LanguageMismatch,
+    /// The plan was created for different user features.
+    FeaturesMismatch,
+    /// The buffer has sideways runs, which need a plan of their own.
+    ///
+    /// Returned for top-to-bottom text with `BufferFlags::ROTATE_SIDEWAYS`.
+    SidewaysRuns,
+}
+
+impl core::fmt::Display for ShapePlanError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ShapePlanError::FaceMismatch => write!(f, "shaping plan was created for a different face"),
+            ShapePlanError::DirectionMismatch => write!(f, "shaping plan was created for a different direction"),
+            ShapePlanError::ScriptMismatch => write!(f, "shaping plan was created for a different script"),
+            ShapePlanError::LanguageMismatch => write!(f, "shaping plan was created for a different language"),
+            ShapePlanError::FeaturesMismatch => write!(f, "shaping plan was created for different features"),
+            ShapePlanError::SidewaysRuns => write!(f, "sideways runs can't be shaped with a single shaping plan"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShapePlanError {}
+
+/// A least recently used cache of shaping plans.
+///
+/// Plans are keyed by the face data, variation coordinates, direction, script,
+/// language and user features.
+///
+/// The cache is bound to the lifetime of the font data, so that the data of a cached
+/// face can't be freed and replaced by another font at the same address
+/// while the cache is alive.
+pub struct PlanCache<'a> {
+    plans: Vec<ShapePlan>,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+    data: PhantomData<&'a [u8]>,
+}
+
+impl<'a> PlanCache<'a> {
+    /// Creates a new cache that holds up to `capacity` plans.
+    ///
+    /// A zero capacity is treated as one.
+    pub fn new(capacity: usize) -> Self {
+        PlanCache {
+            plans: Vec::new(),
+            capacity: capacity.max(1),
+            hits: 0,
+            misses: 0,
+            data: PhantomData,
+        }
+    }
+
+    /// Returns a cached plan or creates a new one.
+    ///
+    /// When the cache is full, the least recently used plan is evicted.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `direction` is `Direction::Invalid`.
+    pub fn get(
+        &mut self,
+        face: &Face<'a>,
+        direction: Direction,
+        script: Option<Script>,
+        language: Option<&Language>,
+        user_features: &[Feature],
+    ) -> &ShapePlan {
+        let found = self.plans.iter().position(|plan| {
+            plan.check(face, direction, script, language, user_features).is_ok()
+        });
+
+        match found {
+            Some(idx) => {
+                self.hits += 1;
+                // Most recently used plans are stored at the end.
+                let plan = self.plans.remove(idx);
+                self.plans.push(plan);
+            }
+            None => {
+                self.misses += 1;
+                if self.plans.len() == self.capacity {
+                    self.plans.remove(0);
+                }
+
+                self.plans.push(ShapePlan::new(face, direction, script, language, user_features));
+            }
+        }
+
+        self.plans.last().unwrap()
+    }
+
+    /// Shapes the buffer content using a cached plan.
+    ///
+    /// Same as [`shape`](crate::shape), but reuses plans between calls.
+    pub fn shape(&mut self, face: &Face<'a>, features: &[Feature], buffer: UnicodeBuffer) -> GlyphBuffer {
+        let mut buffer = buffer.0;
+        buffer.guess_segment_properties();
+
+        if crate::shape::rotates_sideways(&buffer) {
+            crate::shape::shape_vertical_runs(face, &mut buffer, |run| {
+                let plan = self.get(face, run.direction, run.script, run.language.as_ref(), features);
+                crate::shape::shape_with_plan_impl(plan, face, features, run);
+            });
+        } else if buffer.len > 0 {
+            let plan = self.get(
+                face,
+                buffer.direction,
+                buffer.script,
+                buffer.language.as_ref(),
+                features,
+            );
+
+            crate::shape::shape_with_plan_impl(plan, face, features, &mut buffer);
+        }
+
+        GlyphBuffer(buffer)
+    }
+
+    /// Returns the maximum number of cached plans.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached plans.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    /// Checks that the cache is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.plans.is_empty()
+    }
+
+    /// Returns the number of lookups that returned an existing plan.
+    #[inline]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of lookups that had to create a new plan.
+    #[inline]
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Removes all cached plans and resets the statistics.
+    pub fn clear(&mut self) {
+        self.plans.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
 }
 
 pub struct ShapePlanner<'a> {
     pub face: &'a Face<'a>,
     pub direction: Direction,
     pub script: Option<Script>,
+    pub language: Option<Language>,
+    pub user_features: Vec<Feature>,
     pub ot_map: ot::MapBuilder<'a>,
     pub aat_map: aat::MapBuilder,
     pub apply_morx: bool,
@@ -102,6 +354,8 @@ impl<'a> ShapePlanner<'a> {
             face,
             direction,
             script,
+            language: language.cloned(),
+            user_features: Vec::new(),
             ot_map,
             aat_map,
             apply_morx,
@@ -197,6 +451,8 @@ impl<'a> ShapePlanner<'a> {
             }
         }
 
+        self.user_features = user_features.to_vec();
+
         if let Some(func) = self.shaper.override_features {
             func(self);
         }
@@ -278,8 +534,11 @@ impl<'a> ShapePlanner<'a> {
         let apply_trak = requested_tracking && self.face.tables().trak.is_some();
 
         let mut plan = ShapePlan {
+            face_key: self.face.key(),
             direction: self.direction,
             script: self.script,
+            language: self.language.take(),
+            user_features: core::mem::take(&mut self.user_features),
             shaper: self.shaper,
             ot_map,
             aat_map,
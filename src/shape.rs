@@ -1,13 +1,16 @@
+use alloc::vec::Vec;
 use core::convert::TryFrom;
 
 use crate::{aat, ot, fallback, normalize, Direction, Face, Feature, GlyphBuffer, UnicodeBuffer};
+use crate::{LookupSet, TableIndex, VerticalOrientation};
 use crate::buffer::{
     glyph_flag, Buffer, BufferClusterLevel, BufferFlags, BufferScratchFlags, GlyphInfo,
     GlyphPropsFlags,
 };
 use crate::complex::ZeroWidthMarksMode;
-use crate::plan::ShapePlan;
-use crate::unicode::{CharExt, GeneralCategory};
+use crate::ot::feature;
+use crate::plan::{ShapePlan, ShapePlanError};
+use crate::unicode::{CharExt, GeneralCategory, GeneralCategoryExt};
 
 /// Shapes the buffer content using provided font and features.
 ///
@@ -17,7 +20,22 @@ pub fn shape(face: &Face, features: &[Feature], buffer: UnicodeBuffer) -> GlyphB
     let mut buffer = buffer.0;
     buffer.guess_segment_properties();
 
-    if buffer.len > 0 {
+    if rotates_sideways(&buffer) {
+        let mut upright_plan = None;
+        let mut sideways_plan = None;
+        shape_vertical_runs(face, &mut buffer, |run| {
+            let plan = if run.direction.is_vertical() { &mut upright_plan } else { &mut sideways_plan };
+            let plan = plan.get_or_insert_with(|| ShapePlan::new(
+                face,
+                run.direction,
+                run.script,
+                run.language.as_ref(),
+                features,
+            ));
+
+            shape_with_plan_impl(plan, face, features, run);
+        });
+    } else if buffer.len > 0 {
         let plan = ShapePlan::new(
             face,
             buffer.direction,
@@ -26,20 +44,172 @@ pub fn shape(face: &Face, features: &[Feature], buffer: UnicodeBuffer) -> GlyphB
             features,
         );
 
-        // Save the original direction, we use it later.
-        let target_direction = buffer.direction;
-        shape_internal(&mut ShapeContext {
-            plan: &plan,
-            face,
-            buffer: &mut buffer,
-            user_features: features,
-            target_direction,
-        });
+        shape_with_plan_impl(&plan, face, features, &mut buffer);
     }
 
     GlyphBuffer(buffer)
 }
 
+/// Shapes the buffer content using a precomputed shaping plan.
+///
+/// Same as [`shape`], but skips the plan creation.
+///
+/// The plan must be created for the same face, features and buffer properties
+/// (after [`UnicodeBuffer::guess_segment_properties`]). Otherwise an error is returned
+/// together with the unshaped buffer, so its allocation can be reused.
+// The buffer is returned by value, like in the `Ok` case.
+#[allow(clippy::result_large_err)]
+pub fn shape_with_plan(
+    plan: &ShapePlan,
+    face: &Face,
+    features: &[Feature],
+    buffer: UnicodeBuffer,
+) -> Result<GlyphBuffer, (ShapePlanError, UnicodeBuffer)> {
+    let mut buffer = buffer.0;
+    buffer.guess_segment_properties();
+
+    if rotates_sideways(&buffer) {
+        return Err((ShapePlanError::SidewaysRuns, UnicodeBuffer(buffer)));
+    }
+
+    let direction = buffer.direction;
+    if let Err(e) = plan.check(face, direction, buffer.script, buffer.language.as_ref(), features) {
+        return Err((e, UnicodeBuffer(buffer)));
+    }
+
+    if buffer.len > 0 {
+        shape_with_plan_impl(plan, face, features, &mut buffer);
+    }
+
+    Ok(GlyphBuffer(buffer))
+}
+
+pub(crate) fn rotates_sideways(buffer: &Buffer) -> bool {
+    buffer.direction == Direction::TopToBottom && buffer.flags.contains(BufferFlags::ROTATE_SIDEWAYS)
+}
+
+// Shapes upright and sideways runs of top-to-bottom text separately with `shape_run`.
+// Sideways runs are shaped in the horizontal direction of the script
+// and then rotated 90 degrees clockwise around the vertical line.
+pub(crate) fn shape_vertical_runs(face: &Face, buffer: &mut Buffer, mut shape_run: impl FnMut(&mut Buffer)) {
+    let sideways = sideways_chars(face, buffer);
+
+    let horizontal = buffer.script
+        .and_then(Direction::from_script)
+        .unwrap_or(Direction::LeftToRight);
+
+    // Center the em box of sideways glyphs on the vertical line.
+    let extents = face.font_extents(Direction::LeftToRight);
+    let center = (extents.ascender + extents.descender) / 2;
+
+    let mut infos = Vec::with_capacity(buffer.len);
+    let mut positions = Vec::with_capacity(buffer.len);
+
+    let mut start = 0;
+    while start < buffer.len {
+        let is_sideways = sideways[start];
+        let mut end = start + 1;
+        while end < buffer.len && sideways[end] == is_sideways {
+            end += 1;
+        }
+
+        let direction = if is_sideways { horizontal } else { buffer.direction };
+        let mut run = buffer.sub_buffer(start..end, direction);
+        buffer.move_message_func(&mut run);
+        shape_run(&mut run);
+        run.move_message_func(buffer);
+
+        for (info, pos) in run.info[..run.len].iter().zip(&run.pos[..run.len]) {
+            let mut pos = *pos;
+            if is_sideways {
+                pos.set_sideways(true);
+                let (x_advance, x_offset) = (pos.x_advance, pos.x_offset);
+                pos.x_advance = pos.y_advance;
+                pos.y_advance = -x_advance;
+                pos.x_offset = pos.y_offset - center;
+                pos.y_offset = -x_offset;
+            }
+
+            infos.push(*info);
+            positions.push(pos);
+        }
+
+        start = end;
+    }
+
+    buffer.len = infos.len();
+    buffer.info = infos;
+    buffer.pos = positions;
+    buffer.have_positions = true;
+}
+
+// Decides which characters are set sideways in vertical text.
+fn sideways_chars(face: &Face, buffer: &Buffer) -> Vec<bool> {
+    let mut vert_lookups = None;
+    let mut sideways = Vec::with_capacity(buffer.len);
+    for info in &buffer.info[..buffer.len] {
+        let c = info.as_char();
+
+        // Marks and joiners follow their base.
+        if let Some(&prev) = sideways.last() {
+            if c.general_category().is_mark() || c.is_default_ignorable() {
+                sideways.push(prev);
+                continue;
+            }
+        }
+
+        sideways.push(match c.vertical_orientation() {
+            VerticalOrientation::Upright |
+            VerticalOrientation::TransformedUpright => false,
+            VerticalOrientation::Rotated => true,
+            VerticalOrientation::TransformedRotated => {
+                !has_vertical_variant(face, c, &mut vert_lookups)
+            }
+        });
+    }
+
+    sideways
+}
+
+// Checks that a character has a vertical variant,
+// either via the `vert` feature or as a vertical presentation form.
+fn has_vertical_variant(face: &Face, c: char, vert_lookups: &mut Option<LookupSet>) -> bool {
+    let glyph = match face.glyph_index(u32::from(c)) {
+        Some(glyph) => glyph,
+        None => return false,
+    };
+
+    if let Some(gsub) = face.gsub.as_ref() {
+        let lookups = vert_lookups.get_or_insert_with(|| {
+            face.collect_lookups(TableIndex::GSUB, None, None, Some(&[feature::VERTICAL_WRITING]))
+        });
+
+        let covered = lookups.iter()
+            .filter_map(|index| gsub.lookups.get(usize::from(index)))
+            .any(|lookup| lookup.coverage.contains(glyph));
+        if covered {
+            return true;
+        }
+    }
+
+    match c.vertical() {
+        Some(v) => face.has_glyph(u32::from(v)),
+        None => false,
+    }
+}
+
+pub(crate) fn shape_with_plan_impl(plan: &ShapePlan, face: &Face, features: &[Feature], buffer: &mut Buffer) {
+    // Save the original direction, we use it later.
+    let target_direction = buffer.direction;
+    shape_internal(&mut ShapeContext {
+        plan,
+        face,
+        buffer,
+        user_features: features,
+        target_direction,
+    });
+}
+
 struct ShapeContext<'a> {
     plan: &'a ShapePlan,
     face: &'a Face<'a>,
@@ -74,7 +244,10 @@ fn shape_internal(ctx: &mut ShapeContext) {
     ensure_native_direction(ctx.buffer);
 
     if let Some(func) = ctx.plan.shaper.preprocess_text {
-        func(ctx.plan, ctx.face, ctx.buffer);
+        if ctx.buffer.message(ctx.face, format_args!("start preprocess-text")) {
+            func(ctx.plan, ctx.face, ctx.buffer);
+            ctx.buffer.message(ctx.face, format_args!("end preprocess-text"));
+        }
     }
 
     substitute_pre(ctx);
@@ -101,7 +274,10 @@ fn substitute_post(ctx: &mut ShapeContext) {
     }
 
     if let Some(func) = ctx.plan.shaper.postprocess_glyphs {
-        func(ctx.plan, ctx.face, ctx.buffer);
+        if ctx.buffer.message(ctx.face, format_args!("start postprocess-glyphs")) {
+            func(ctx.plan, ctx.face, ctx.buffer);
+            ctx.buffer.message(ctx.face, format_args!("end postprocess-glyphs"));
+        }
     }
 }
 
@@ -157,12 +333,18 @@ fn position_default(ctx: &mut ShapeContext) {
         for (info, pos) in ctx.buffer.info[..len].iter().zip(&mut ctx.buffer.pos[..len]) {
             pos.x_advance = ctx.face.glyph_h_advance(info.as_glyph());
         }
+
+        // The default horizontal origin is always zero.
+        if ctx.face.has_font_funcs() {
+            subtract_h_origins(ctx.face, ctx.buffer);
+        }
     } else {
         for (info, pos) in ctx.buffer.info[..len].iter().zip(&mut ctx.buffer.pos[..len]) {
             let glyph = info.as_glyph();
+            let (x, y) = ctx.face.glyph_v_origin(glyph);
             pos.y_advance = ctx.face.glyph_v_advance(glyph);
-            pos.x_offset -= ctx.face.glyph_h_origin(glyph);
-            pos.y_offset -= ctx.face.glyph_v_origin(glyph);
+            pos.x_offset -= x;
+            pos.y_offset -= y;
         }
     }
 
@@ -184,6 +366,9 @@ fn position_complex(ctx: &mut ShapeContext) {
         && ctx.buffer.direction.is_forward();
 
     // We change glyph origin to what GPOS expects (horizontal), apply GPOS, change it back.
+    if ctx.face.has_font_funcs() {
+        add_h_origins(ctx.face, ctx.buffer);
+    }
 
     ot::position_start(ctx.face, ctx.buffer);
 
@@ -207,11 +392,33 @@ fn position_complex(ctx: &mut ShapeContext) {
 
     ot::position_finish_offsets(ctx.face, ctx.buffer);
 
+    if ctx.face.has_font_funcs() {
+        subtract_h_origins(ctx.face, ctx.buffer);
+    }
+
     if ctx.plan.fallback_mark_positioning {
         fallback::position_marks(ctx.plan, ctx.face, ctx.buffer, adjust_offsets_when_zeroing);
     }
 }
 
+fn add_h_origins(face: &Face, buffer: &mut Buffer) {
+    let len = buffer.len;
+    for (info, pos) in buffer.info[..len].iter().zip(&mut buffer.pos[..len]) {
+        let (x, y) = face.glyph_h_origin(info.as_glyph());
+        pos.x_offset += x;
+        pos.y_offset += y;
+    }
+}
+
+fn subtract_h_origins(face: &Face, buffer: &mut Buffer) {
+    let len = buffer.len;
+    for (info, pos) in buffer.info[..len].iter().zip(&mut buffer.pos[..len]) {
+        let (x, y) = face.glyph_h_origin(info.as_glyph());
+        pos.x_offset -= x;
+        pos.y_offset -= y;
+    }
+}
+
 fn position_by_plan(plan: &ShapePlan, face: &Face, buffer: &mut Buffer) {
     if plan.apply_gpos {
         ot::position(plan, face, buffer);
@@ -371,9 +578,9 @@ fn insert_dotted_circle(buffer: &mut Buffer, face: &Face) {
 
 fn form_clusters(buffer: &mut Buffer) {
     if buffer.scratch_flags.contains(BufferScratchFlags::HAS_NON_ASCII) {
-        if buffer.cluster_level == BufferClusterLevel::MonotoneGraphemes {
+        if buffer.cluster_level.is_graphemes() {
             foreach_grapheme!(buffer, start, end, {
-                buffer.merge_clusters(start, end)
+                buffer.merge_grapheme_clusters(start, end)
             });
         } else {
             foreach_grapheme!(buffer, start, end, {
@@ -521,16 +728,42 @@ fn hide_default_ignorables(buffer: &mut Buffer, face: &Face) {
 fn propagate_flags(buffer: &mut Buffer) {
     // Propagate cluster-level glyph flags to be the same on all cluster glyphs.
     // Simplifies using them.
-    if buffer.scratch_flags.contains(BufferScratchFlags::HAS_UNSAFE_TO_BREAK) {
-        foreach_cluster!(buffer, start, end, {
-            for info in &buffer.info[start..end] {
-                if info.mask & glyph_flag::UNSAFE_TO_BREAK != 0 {
-                    for info in &mut buffer.info[start..end] {
-                        info.mask |= glyph_flag::UNSAFE_TO_BREAK;
-                    }
-                    break;
-                }
-            }
-        });
+    if !buffer.scratch_flags.contains(BufferScratchFlags::HAS_GLYPH_FLAGS) {
+        return;
     }
+
+    // If we are producing SAFE_TO_INSERT_TATWEEL, then do two things:
+    //
+    // - If the places that the Arabic shaper marked as SAFE_TO_INSERT_TATWEEL,
+    //   are UNSAFE_TO_BREAK, then clear the SAFE_TO_INSERT_TATWEEL,
+    // - Any place that is SAFE_TO_INSERT_TATWEEL, is also now UNSAFE_TO_BREAK.
+    //
+    // We couldn't make this interaction earlier. It has to be done here.
+    let flip_tatweel = buffer.flags.contains(BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL);
+    let clear_concat = !buffer.flags.contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+
+    foreach_cluster!(buffer, start, end, {
+        let mut mask = 0;
+        for info in &buffer.info[start..end] {
+            mask |= info.mask & glyph_flag::DEFINED;
+        }
+
+        if flip_tatweel {
+            if mask & glyph_flag::UNSAFE_TO_BREAK != 0 {
+                mask &= !glyph_flag::SAFE_TO_INSERT_TATWEEL;
+            }
+
+            if mask & glyph_flag::SAFE_TO_INSERT_TATWEEL != 0 {
+                mask |= glyph_flag::UNSAFE_TO_BREAK | glyph_flag::UNSAFE_TO_CONCAT;
+            }
+        }
+
+        if clear_concat {
+            mask &= !glyph_flag::UNSAFE_TO_CONCAT;
+        }
+
+        for info in &mut buffer.info[start..end] {
+            info.mask = (info.mask & !glyph_flag::DEFINED) | mask;
+        }
+    });
 }
@@ -101,6 +101,14 @@ impl<'a> TextParser<'a> {
         self.text[start..self.pos].parse::<i32>().ok()
     }
 
+    pub fn consume_u32(&mut self) -> Option<u32> {
+        self.consume_bytes(|c| c.is_ascii_digit()).parse::<u32>().ok()
+    }
+
+    pub fn consume_hex_u32(&mut self) -> Option<u32> {
+        u32::from_str_radix(self.consume_bytes(|c| c.is_ascii_hexdigit()), 16).ok()
+    }
+
     pub fn consume_f32(&mut self) -> Option<f32> {
         let start = self.pos;
 
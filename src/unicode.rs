@@ -3,7 +3,7 @@ use core::convert::TryFrom;
 pub use unicode_general_category::GeneralCategory;
 pub use unicode_ccc::CanonicalCombiningClass; // TODO: prefer unic-ucd-normal::CanonicalCombiningClass
 
-use crate::Script;
+use crate::{Script, VerticalOrientation};
 
 // Space estimates based on:
 // https://unicode.org/charts/PDF/U2000.pdf
@@ -313,6 +313,7 @@ pub trait CharExt {
     fn is_default_ignorable(self) -> bool;
     fn is_variation_selector(self) -> bool;
     fn vertical(self) -> Option<char>;
+    fn vertical_orientation(self) -> VerticalOrientation;
 }
 
 impl CharExt for char {
@@ -752,6 +753,199 @@ impl CharExt for char {
             _ => return None,
         })
     }
+
+    fn vertical_orientation(self) -> VerticalOrientation {
+        use VerticalOrientation::*;
+
+        // Generated by scripts/gen-unicode-vertical-orientation.py from Unicode 15.0.0,
+        // the version of the general category and script data.
+        match self as u32 {
+            0x00A7 => Upright,
+            0x00A9 => Upright,
+            0x00AE => Upright,
+            0x00B1 => Upright,
+            0x00BC..=0x00BE => Upright,
+            0x00D7 => Upright,
+            0x00F7 => Upright,
+            0x02EA..=0x02EB => Upright,
+            0x1100..=0x11FF => Upright,
+            0x1401..=0x167F => Upright,
+            0x18B0..=0x18FF => Upright,
+            0x2016 => Upright,
+            0x2020..=0x2021 => Upright,
+            0x2030..=0x2031 => Upright,
+            0x203B..=0x203C => Upright,
+            0x2042 => Upright,
+            0x2047..=0x2049 => Upright,
+            0x2051 => Upright,
+            0x2065 => Upright,
+            0x20DD..=0x20E0 => Upright,
+            0x20E2..=0x20E4 => Upright,
+            0x2100..=0x2101 => Upright,
+            0x2103..=0x2109 => Upright,
+            0x210F => Upright,
+            0x2113..=0x2114 => Upright,
+            0x2116..=0x2117 => Upright,
+            0x211E..=0x2123 => Upright,
+            0x2125 => Upright,
+            0x2127 => Upright,
+            0x2129 => Upright,
+            0x212E => Upright,
+            0x2135..=0x213F => Upright,
+            0x2145..=0x214A => Upright,
+            0x214C..=0x214D => Upright,
+            0x214F..=0x2189 => Upright,
+            0x218C..=0x218F => Upright,
+            0x221E => Upright,
+            0x2234..=0x2235 => Upright,
+            0x2300..=0x2307 => Upright,
+            0x230C..=0x231F => Upright,
+            0x2324..=0x2328 => Upright,
+            0x2329..=0x232A => TransformedRotated,
+            0x232B => Upright,
+            0x237D..=0x239A => Upright,
+            0x23BE..=0x23CD => Upright,
+            0x23CF => Upright,
+            0x23D1..=0x23DB => Upright,
+            0x23E2..=0x2422 => Upright,
+            0x2424..=0x24FF => Upright,
+            0x25A0..=0x2619 => Upright,
+            0x2620..=0x2767 => Upright,
+            0x2776..=0x2793 => Upright,
+            0x2B12..=0x2B2F => Upright,
+            0x2B50..=0x2B59 => Upright,
+            0x2BB8..=0x2BD1 => Upright,
+            0x2BD3..=0x2BEB => Upright,
+            0x2BF0..=0x2BFF => Upright,
+            0x2E50..=0x2E51 => Upright,
+            0x2E80..=0x3000 => Upright,
+            0x3001..=0x3002 => TransformedUpright,
+            0x3003..=0x3007 => Upright,
+            0x3008..=0x3011 => TransformedRotated,
+            0x3012..=0x3013 => Upright,
+            0x3014..=0x301F => TransformedRotated,
+            0x3020..=0x302F => Upright,
+            0x3030 => TransformedRotated,
+            0x3031..=0x3040 => Upright,
+            0x3041 => TransformedUpright,
+            0x3042 => Upright,
+            0x3043 => TransformedUpright,
+            0x3044 => Upright,
+            0x3045 => TransformedUpright,
+            0x3046 => Upright,
+            0x3047 => TransformedUpright,
+            0x3048 => Upright,
+            0x3049 => TransformedUpright,
+            0x304A..=0x3062 => Upright,
+            0x3063 => TransformedUpright,
+            0x3064..=0x3082 => Upright,
+            0x3083 => TransformedUpright,
+            0x3084 => Upright,
+            0x3085 => TransformedUpright,
+            0x3086 => Upright,
+            0x3087 => TransformedUpright,
+            0x3088..=0x308D => Upright,
+            0x308E => TransformedUpright,
+            0x308F..=0x3094 => Upright,
+            0x3095..=0x3096 => TransformedUpright,
+            0x3097..=0x309A => Upright,
+            0x309B..=0x309C => TransformedUpright,
+            0x309D..=0x309F => Upright,
+            0x30A0 => TransformedRotated,
+            0x30A1 => TransformedUpright,
+            0x30A2 => Upright,
+            0x30A3 => TransformedUpright,
+            0x30A4 => Upright,
+            0x30A5 => TransformedUpright,
+            0x30A6 => Upright,
+            0x30A7 => TransformedUpright,
+            0x30A8 => Upright,
+            0x30A9 => TransformedUpright,
+            0x30AA..=0x30C2 => Upright,
+            0x30C3 => TransformedUpright,
+            0x30C4..=0x30E2 => Upright,
+            0x30E3 => TransformedUpright,
+            0x30E4 => Upright,
+            0x30E5 => TransformedUpright,
+            0x30E6 => Upright,
+            0x30E7 => TransformedUpright,
+            0x30E8..=0x30ED => Upright,
+            0x30EE => TransformedUpright,
+            0x30EF..=0x30F4 => Upright,
+            0x30F5..=0x30F6 => TransformedUpright,
+            0x30F7..=0x30FB => Upright,
+            0x30FC => TransformedRotated,
+            0x30FD..=0x31EF => Upright,
+            0x31F0..=0x31FF => TransformedUpright,
+            0x3200..=0x32FF => Upright,
+            0x3300..=0x3357 => TransformedUpright,
+            0x3358..=0x337A => Upright,
+            0x337B..=0x337F => TransformedUpright,
+            0x3380..=0xA4CF => Upright,
+            0xA960..=0xA97F => Upright,
+            0xAC00..=0xD7FF => Upright,
+            0xE000..=0xFAFF => Upright,
+            0xFE10..=0xFE1F => Upright,
+            0xFE30..=0xFE48 => Upright,
+            0xFE50..=0xFE52 => TransformedUpright,
+            0xFE53..=0xFE57 => Upright,
+            0xFE59..=0xFE5E => TransformedRotated,
+            0xFE5F..=0xFE62 => Upright,
+            0xFE64..=0xFE66 => Upright,
+            0xFE68..=0xFE6B => Upright,
+            0xFF00 => Upright,
+            0xFF01 => TransformedUpright,
+            0xFF02..=0xFF07 => Upright,
+            0xFF08..=0xFF09 => TransformedRotated,
+            0xFF0A..=0xFF0B => Upright,
+            0xFF0C => TransformedUpright,
+            0xFF0E => TransformedUpright,
+            0xFF0F..=0xFF19 => Upright,
+            0xFF1A..=0xFF1B => TransformedRotated,
+            0xFF1F => TransformedUpright,
+            0xFF20..=0xFF3A => Upright,
+            0xFF3B => TransformedRotated,
+            0xFF3C => Upright,
+            0xFF3D => TransformedRotated,
+            0xFF3E => Upright,
+            0xFF3F => TransformedRotated,
+            0xFF40..=0xFF5A => Upright,
+            0xFF5B..=0xFF60 => TransformedRotated,
+            0xFFE0..=0xFFE2 => Upright,
+            0xFFE3 => TransformedRotated,
+            0xFFE4..=0xFFE7 => Upright,
+            0xFFF0..=0xFFF8 => Upright,
+            0xFFFC..=0xFFFD => Upright,
+            0x10980..=0x1099F => Upright,
+            0x11580..=0x115FF => Upright,
+            0x11A00..=0x11AAF => Upright,
+            0x13000..=0x1345F => Upright,
+            0x14400..=0x1467F => Upright,
+            0x16FE0..=0x18D7F => Upright,
+            0x1AFF0..=0x1B131 => Upright,
+            0x1B132 => TransformedUpright,
+            0x1B133..=0x1B14F => Upright,
+            0x1B150..=0x1B152 => TransformedUpright,
+            0x1B153..=0x1B154 => Upright,
+            0x1B155 => TransformedUpright,
+            0x1B156..=0x1B163 => Upright,
+            0x1B164..=0x1B167 => TransformedUpright,
+            0x1B168..=0x1B2FF => Upright,
+            0x1CF00..=0x1CFCF => Upright,
+            0x1D000..=0x1D1FF => Upright,
+            0x1D2E0..=0x1D37F => Upright,
+            0x1D800..=0x1DAAF => Upright,
+            0x1F000..=0x1F1FF => Upright,
+            0x1F200..=0x1F201 => TransformedUpright,
+            0x1F202..=0x1F7FF => Upright,
+            0x1F900..=0x1FAFF => Upright,
+            0x20000..=0x2FFFD => Upright,
+            0x30000..=0x3FFFD => Upright,
+            0xF0000..=0xFFFFD => Upright,
+            0x100000..=0x10FFFD => Upright,
+            _ => Rotated,
+        }
+    }
 }
 
 const S_BASE: u32 = 0xAC00;
@@ -0,0 +1,1085 @@
+use rustybuzz::{
+    BufferClusterLevel, BufferFlags, ClusterUnit, Direction, Face, Feature, PlanCache, SerializeFlags, ShapePlan, ShapePlanError, Tag,
+    UnicodeBuffer,
+};
+
+const SOURCE_SANS: &str = "benches/fonts/SourceSansPro-Regular.ttf";
+
+// Faces borrow their data, so it's kept alive until the end of the test run.
+fn load_face(path: &str) -> Face<'static> {
+    let data = std::fs::read(path).unwrap();
+    Face::from_slice(Box::leak(data.into_boxed_slice()), 0).unwrap()
+}
+
+fn make_buffer(text: &str) -> UnicodeBuffer {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer
+}
+
+#[test]
+fn shape_with_plan() {
+    let face = load_face(SOURCE_SANS);
+    let features = [Feature::new(Tag::from_bytes(b"liga"), 0, ..)];
+
+    let buffer = make_buffer("office");
+    let plan = ShapePlan::new(
+        &face,
+        buffer.direction(),
+        Some(buffer.script()),
+        buffer.language().as_ref(),
+        &features,
+    );
+
+    let expected = rustybuzz::shape(&face, &features, make_buffer("office"));
+    for _ in 0..2 {
+        let glyphs = rustybuzz::shape_with_plan(&plan, &face, &features, make_buffer("office")).unwrap();
+        assert_eq!(
+            glyphs.serialize(&face, SerializeFlags::default()),
+            expected.serialize(&face, SerializeFlags::default()),
+        );
+    }
+}
+
+#[test]
+fn shape_with_mismatched_plan() {
+    let face = load_face(SOURCE_SANS);
+    let buffer = make_buffer("office");
+    let plan = ShapePlan::new(&face, Direction::LeftToRight, Some(buffer.script()), None, &[]);
+
+    let mut rtl = make_buffer("office");
+    rtl.set_direction(Direction::RightToLeft);
+    let (error, rtl) = rustybuzz::shape_with_plan(&plan, &face, &[], rtl).err().unwrap();
+    assert_eq!(error, ShapePlanError::DirectionMismatch);
+
+    // The buffer is returned untouched.
+    assert_eq!(rtl.len(), 6);
+    assert_eq!(rtl.direction(), Direction::RightToLeft);
+    assert_eq!(rustybuzz::shape(&face, &[], rtl).len(), 5);
+
+    let features = [Feature::new(Tag::from_bytes(b"liga"), 0, ..)];
+    assert_eq!(
+        rustybuzz::shape_with_plan(&plan, &face, &features, make_buffer("office")).err().map(|e| e.0),
+        Some(ShapePlanError::FeaturesMismatch),
+    );
+
+    let amiri = load_face("benches/fonts/Amiri-Regular.ttf");
+    assert_eq!(
+        rustybuzz::shape_with_plan(&plan, &amiri, &[], make_buffer("office")).err().map(|e| e.0),
+        Some(ShapePlanError::FaceMismatch),
+    );
+}
+
+#[test]
+fn plan_cache() {
+    let face = load_face(SOURCE_SANS);
+    let features = [Feature::new(Tag::from_bytes(b"liga"), 0, ..)];
+
+    let mut cache = PlanCache::new(2);
+    let expected = rustybuzz::shape(&face, &[], make_buffer("office"));
+    let glyphs = cache.shape(&face, &[], make_buffer("office"));
+    assert_eq!(
+        glyphs.serialize(&face, SerializeFlags::default()),
+        expected.serialize(&face, SerializeFlags::default()),
+    );
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+    cache.shape(&face, &[], make_buffer("fish"));
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+    cache.shape(&face, &features, make_buffer("office"));
+    cache.shape(&face, &[], make_buffer("\u{0627}\u{0644}"));
+    assert_eq!((cache.hits(), cache.misses()), (1, 3));
+    assert_eq!(cache.len(), 2);
+
+    // The plan without features was evicted by the Arabic one.
+    cache.shape(&face, &[], make_buffer("office"));
+    assert_eq!((cache.hits(), cache.misses()), (1, 4));
+
+    // Another face with data that outlives the cache.
+    let amiri = load_face("benches/fonts/Amiri-Regular.ttf");
+    cache.shape(&amiri, &[], make_buffer("office"));
+    assert_eq!((cache.hits(), cache.misses()), (1, 5));
+}
+
+#[test]
+fn buffer_flags() {
+    let face = load_face(SOURCE_SANS);
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer("\u{0301}a"));
+    assert_eq!(glyphs.len(), 2);
+
+    let mut buffer = make_buffer("\u{0301}a");
+    buffer.set_flags(BufferFlags::BEGINNING_OF_TEXT);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.len(), 3);
+
+    let mut buffer = make_buffer("\u{0301}a");
+    buffer.set_flags(BufferFlags::BEGINNING_OF_TEXT | BufferFlags::DO_NOT_INSERT_DOTTED_CIRCLE);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.len(), 2);
+
+    let mut buffer = make_buffer("a\u{200B}b");
+    buffer.set_flags(BufferFlags::REMOVE_DEFAULT_IGNORABLES);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.len(), 2);
+}
+
+#[test]
+fn push_str_with_context() {
+    let face = load_face("benches/fonts/Amiri-Regular.ttf");
+    let glyph_name = |glyphs: &rustybuzz::GlyphBuffer| {
+        let glyph_id = rustybuzz::ttf_parser::GlyphId(glyphs.glyph_infos()[0].glyph_id as u16);
+        face.glyph_name(glyph_id).unwrap().to_string()
+    };
+
+    let text = "\u{0628}\u{0628}\u{0628}";
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str_with_context(text, 2..4);
+    buffer.guess_segment_properties();
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.len(), 1);
+    assert_eq!(glyphs.glyph_infos()[0].cluster, 2);
+    assert_eq!(glyph_name(&glyphs), "uni0628.medi");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str_with_context(text, 4..6);
+    buffer.guess_segment_properties();
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyph_name(&glyphs), "uni0628.fina");
+
+    // Long enough for the pre-context to be decoded from the middle of a char.
+    let text = "\u{0628}".repeat(13);
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str_with_context(&text, 24..26);
+    buffer.guess_segment_properties();
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyph_name(&glyphs), "uni0628.fina");
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer("\u{0628}"));
+    assert_eq!(glyph_name(&glyphs), "uni0628");
+}
+
+fn shape_with_names(face: &Face, buffer: UnicodeBuffer) -> String {
+    let mut buffer = buffer;
+    buffer.guess_segment_properties();
+    let glyphs = rustybuzz::shape(face, &[], buffer);
+    glyphs.serialize(face, SerializeFlags::NO_POSITIONS)
+}
+
+#[test]
+fn push_utf8() {
+    let face = load_face(SOURCE_SANS);
+
+    let text = "a\u{00E9}b".as_bytes();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf8(text, 0..text.len(), ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|eacute=1|b=3");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf8(text, 0..text.len(), ClusterUnit::Codepoint);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|eacute=1|b=2");
+
+    // A stray byte and a truncated sequence.
+    let text = b"a\xFFb\xE2\x82";
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf8(text, 0..text.len(), ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|b=2|.notdef=3");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf8(text, 0..text.len(), ClusterUnit::Utf16);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|b=2|.notdef=3");
+}
+
+#[test]
+fn push_utf16() {
+    let face = load_face(SOURCE_SANS);
+
+    let text: Vec<u16> = "a\u{1F600}b".encode_utf16().collect();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf16(&text, 0..text.len(), ClusterUnit::Utf16);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|b=3");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf16(&text, 0..text.len(), ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|b=5");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf16(&text, 3..4, ClusterUnit::Codepoint);
+    assert_eq!(shape_with_names(&face, buffer), "b=2");
+
+    // An unpaired surrogate.
+    let text = [0x61, 0xD800, 0x62];
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf16(&text, 0..text.len(), ClusterUnit::Utf16);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|b=2");
+}
+
+#[test]
+fn push_codepoints() {
+    let face = load_face(SOURCE_SANS);
+
+    let text = [0x61, 0xD800, 0x110000, 0xE9, 0x62];
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_codepoints(&text, 0..text.len(), ClusterUnit::Codepoint);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|.notdef=2|eacute=3|b=4");
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_codepoints(&text, 0..text.len(), ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "a=0|.notdef=1|.notdef=4|eacute=7|b=9");
+}
+
+#[test]
+fn push_utf16_with_context() {
+    let face = load_face("benches/fonts/Amiri-Regular.ttf");
+
+    let text: Vec<u16> = "\u{0628}\u{0628}\u{0628}".encode_utf16().collect();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf16(&text, 1..2, ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "uni0628.medi=2");
+
+    // Context decoding can start in the middle of a char.
+    let text = "\u{0628}".repeat(13);
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_utf8(text.as_bytes(), 24..26, ClusterUnit::Utf8);
+    assert_eq!(shape_with_names(&face, buffer), "uni0628.fina=24");
+}
+
+#[test]
+fn unsafe_to_concat_flag() {
+    let face = load_face("benches/fonts/Amiri-Regular.ttf");
+    let text = "\u{0628}\u{0628} \u{0627}\u{0644}";
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    assert!(glyphs.glyph_infos().iter().all(|info| !info.unsafe_to_concat()));
+
+    let mut buffer = make_buffer(text);
+    buffer.set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert!(glyphs.glyph_infos().iter().any(|info| info.unsafe_to_concat()));
+    assert!(glyphs.glyph_infos().iter().all(|info| !info.unsafe_to_break() || info.unsafe_to_concat()));
+}
+
+#[test]
+fn safe_to_insert_tatweel_flag() {
+    let face = load_face("benches/fonts/Amiri-Regular.ttf");
+    let text = "\u{0633}\u{0633}";
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    assert!(glyphs.glyph_infos().iter().all(|info| !info.safe_to_insert_tatweel()));
+
+    let mut buffer = make_buffer(text);
+    buffer.set_flags(BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let infos = glyphs.glyph_infos();
+    // Glyphs are in visual order.
+    assert!(infos[0].safe_to_insert_tatweel() && infos[0].unsafe_to_break());
+    assert!(!infos[1].safe_to_insert_tatweel());
+}
+
+fn shape_clusters(face: &Face, text: &str, level: BufferClusterLevel) -> Vec<u32> {
+    let mut buffer = make_buffer(text);
+    buffer.set_cluster_level(level);
+    let glyphs = rustybuzz::shape(face, &[], buffer);
+    glyphs.glyph_infos().iter().map(|info| info.cluster).collect()
+}
+
+#[test]
+fn cluster_levels() {
+    let face = load_face(SOURCE_SANS);
+    // Marks are reordered by normalization and `ff` forms a ligature.
+    let text = "ax\u{0301}\u{0323}ff";
+
+    assert_eq!(shape_clusters(&face, text, BufferClusterLevel::MonotoneGraphemes), [0, 1, 1, 1, 6]);
+    assert_eq!(shape_clusters(&face, text, BufferClusterLevel::MonotoneCharacters), [0, 1, 2, 2, 6]);
+    assert_eq!(shape_clusters(&face, text, BufferClusterLevel::Characters), [0, 1, 4, 2, 6]);
+    assert_eq!(shape_clusters(&face, text, BufferClusterLevel::Graphemes), [0, 1, 1, 1, 6]);
+}
+
+#[test]
+fn characters_cluster_level_marks_unsafe_to_break() {
+    let face = load_face(SOURCE_SANS);
+
+    let mut buffer = make_buffer("ax\u{0301}");
+    buffer.set_cluster_level(BufferClusterLevel::Characters);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let infos = glyphs.glyph_infos();
+    assert_eq!(infos.iter().map(|info| info.cluster).collect::<Vec<_>>(), [0, 1, 2]);
+    // Only the glyphs that don't start the grapheme are marked.
+    assert!(!infos[0].unsafe_to_break() && !infos[1].unsafe_to_break());
+    assert!(infos[2].unsafe_to_break());
+}
+
+#[test]
+fn ligature_component_count() {
+    let face = load_face(SOURCE_SANS);
+
+    let mut buffer = make_buffer("office");
+    buffer.set_cluster_level(BufferClusterLevel::Characters);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let counts: Vec<_> = glyphs.glyph_infos().iter().map(|info| info.ligature_component_count()).collect();
+    assert_eq!(counts, [1, 2, 1, 1, 1]);
+}
+
+struct ScaledFuncs;
+
+impl rustybuzz::FontFuncs for ScaledFuncs {
+    fn nominal_glyph(&self, face: &Face, c: char) -> Option<rustybuzz::ttf_parser::GlyphId> {
+        // Render all `a`s as `b`s.
+        let c = if c == 'a' { 'b' } else { c };
+        rustybuzz::DefaultFontFuncs.nominal_glyph(face, c)
+    }
+
+    fn glyph_h_advance(&self, face: &Face, glyph: rustybuzz::ttf_parser::GlyphId) -> i32 {
+        rustybuzz::DefaultFontFuncs.glyph_h_advance(face, glyph) * 2
+    }
+
+    fn glyph_h_origin(&self, _: &Face, _: rustybuzz::ttf_parser::GlyphId) -> (i32, i32) {
+        (10, 20)
+    }
+
+    fn glyph_extents(&self, _: &Face, _: rustybuzz::ttf_parser::GlyphId) -> Option<rustybuzz::GlyphExtents> {
+        Some(rustybuzz::GlyphExtents { x_bearing: 1, y_bearing: 2, width: 3, height: -4 })
+    }
+}
+
+#[test]
+fn font_funcs() {
+    let mut face = load_face(SOURCE_SANS);
+    let flags = SerializeFlags::GLYPH_EXTENTS;
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer("ab"));
+    assert_eq!(glyphs.serialize(&face, flags), "a=0+504<52,498,381,-510>|b=1+554<82,712,426,-724>");
+
+    let funcs = ScaledFuncs;
+    face.set_font_funcs(Some(&funcs));
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer("ab"));
+    assert_eq!(glyphs.serialize(&face, flags), "b=0@-10,-20+1108<1,2,3,-4>|b=1@-10,-20+1108<1,2,3,-4>");
+
+    // The default vertical origin uses the custom horizontal advance.
+    let mut buffer = make_buffer("b");
+    buffer.set_direction(Direction::TopToBottom);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let mut face_without_funcs = face.clone();
+    face_without_funcs.set_font_funcs(None);
+    let mut buffer = make_buffer("b");
+    buffer.set_direction(Direction::TopToBottom);
+    let default_glyphs = rustybuzz::shape(&face_without_funcs, &[], buffer);
+    assert_eq!(glyphs.glyph_positions()[0].x_offset, default_glyphs.glyph_positions()[0].x_offset * 2);
+}
+
+struct ContourPointFuncs;
+
+impl rustybuzz::FontFuncs for ContourPointFuncs {
+    fn glyph_contour_point(&self, _: &Face, glyph: rustybuzz::ttf_parser::GlyphId, point: u16) -> Option<(i32, i32)> {
+        Some((i32::from(point) * 10, i32::from(glyph.0) * 100))
+    }
+}
+
+#[test]
+fn font_funcs_contour_point() {
+    // Has format 2 anchors.
+    let mut face = load_face("tests/fonts/in-house/5028afb650b1bb718ed2131e872fbcce57828fff.ttf");
+    // Keep the virama separate.
+    let features = [Feature::new(Tag::from_bytes(b"haln"), 0, ..)];
+    let text = "\u{0B1F}\u{0B4D}";
+    let flags = SerializeFlags::default();
+    let expected = "ttaorya=0+1307|viramaorya=0@-242,0+0";
+
+    // Contour points are used only when pixels per EM are set.
+    let funcs = ContourPointFuncs;
+    face.set_font_funcs(Some(&funcs));
+    let glyphs = rustybuzz::shape(&face, &features, make_buffer(text));
+    assert_eq!(glyphs.serialize(&face, flags), expected);
+
+    face.set_pixels_per_em(Some((16, 16)));
+    let glyphs = rustybuzz::shape(&face, &features, make_buffer(text));
+    assert_eq!(glyphs.serialize(&face, flags), "ttaorya=0+1307|viramaorya=0@-817,-100+0");
+
+    // The default functions have no contour points.
+    face.set_font_funcs(None);
+    let glyphs = rustybuzz::shape(&face, &features, make_buffer(text));
+    assert_eq!(glyphs.serialize(&face, flags), expected);
+}
+
+#[test]
+fn face_scale() {
+    let mut face = load_face("benches/fonts/Amiri-Regular.ttf");
+    let text = "\u{0644}\u{064E}\u{0627} AVATAR";
+    assert_eq!(face.scale(), (2048, 2048));
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let expected: Vec<_> = glyphs.glyph_positions().iter()
+        .map(|pos| (pos.x_advance * 2, pos.y_advance * 2, pos.x_offset * 2, pos.y_offset * 2))
+        .collect();
+    assert!(expected.iter().any(|pos| pos.2 != 0));
+
+    face.set_scale(4096, 4096);
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let positions: Vec<_> = glyphs.glyph_positions().iter()
+        .map(|pos| (pos.x_advance, pos.y_advance, pos.x_offset, pos.y_offset))
+        .collect();
+    assert_eq!(positions, expected);
+}
+
+#[test]
+fn synthetic_bold() {
+    let mut face = load_face(SOURCE_SANS);
+    let text = "x\u{0301} AV";
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let expected: Vec<_> = glyphs.glyph_positions().iter()
+        .map(|pos| if pos.x_advance != 0 { pos.x_advance + 20 } else { 0 })
+        .collect();
+
+    face.set_synthetic_bold(0.02, 0.02, false);
+    assert_eq!(face.synthetic_bold(), (0.02, 0.02, false));
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let advances: Vec<_> = glyphs.glyph_positions().iter().map(|pos| pos.x_advance).collect();
+    assert_eq!(advances, expected);
+
+    face.set_synthetic_bold(0.02, 0.02, true);
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let advances: Vec<_> = glyphs.glyph_positions().iter().map(|pos| pos.x_advance).collect();
+    let expected: Vec<_> = expected.iter().map(|&adv| if adv != 0 { adv - 20 } else { 0 }).collect();
+    assert_eq!(advances, expected);
+}
+
+#[test]
+fn synthetic_slant() {
+    let mut face = load_face("tests/fonts/in-house/bf39b0e91ef9807f15a9e283a21a14a209fd2cfc.ttf");
+    let text = "\u{0644}\u{064E}\u{0670}\u{0653}\u{0626}";
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let offsets: Vec<_> = glyphs.glyph_positions().iter().map(|pos| (pos.x_offset, pos.y_offset)).collect();
+    assert_eq!(offsets, [(0, 0), (50, 350), (550, 1425), (0, 0)]);
+
+    face.set_synthetic_slant(0.2);
+    assert_eq!(face.synthetic_slant(), 0.2);
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer(text));
+    let offsets: Vec<_> = glyphs.glyph_positions().iter().map(|pos| (pos.x_offset, pos.y_offset)).collect();
+    assert_eq!(offsets, [(0, 0), (120, 350), (835, 1425), (0, 0)]);
+}
+
+#[test]
+fn glyph_metrics() {
+    let mut face = load_face(SOURCE_SANS);
+
+    let glyphs = rustybuzz::shape(&face, &[], make_buffer("A"));
+    let glyph = rustybuzz::ttf_parser::GlyphId(glyphs.glyph_infos()[0].glyph_id as u16);
+    let advance = glyphs.glyph_positions()[0].x_advance;
+
+    assert_eq!(face.glyph_name(glyph), Some("A"));
+    assert_eq!(face.glyph_h_advance(glyph), advance);
+    assert_eq!(face.glyph_v_advance(glyph), -1000);
+    assert_eq!(face.glyph_h_origin(glyph), (0, 0));
+    assert_eq!(face.glyph_v_origin(glyph).0, advance / 2);
+
+    let extents = face.glyph_extents(glyph).unwrap();
+    assert!(extents.width > 0 && extents.height < 0);
+
+    face.set_scale(2000, 2000);
+    assert_eq!(face.glyph_h_advance(glyph), advance * 2);
+    assert_eq!(face.glyph_v_advance(glyph), -2000);
+    let scaled = face.glyph_extents(glyph).unwrap();
+    assert_eq!(scaled.x_bearing, extents.x_bearing * 2);
+    assert_eq!(scaled.width, extents.width * 2);
+}
+
+#[test]
+fn font_extents() {
+    use rustybuzz::FontExtents;
+
+    let mut face = load_face(SOURCE_SANS);
+    assert_eq!(
+        face.font_extents(Direction::LeftToRight),
+        FontExtents { ascender: 1024, descender: -400, line_gap: 0 }
+    );
+    // No `vhea`.
+    assert_eq!(
+        face.font_extents(Direction::TopToBottom),
+        FontExtents { ascender: 500, descender: -500, line_gap: 0 }
+    );
+
+    face.set_scale(2000, 2000);
+    assert_eq!(
+        face.font_extents(Direction::RightToLeft),
+        FontExtents { ascender: 2048, descender: -800, line_gap: 0 }
+    );
+
+    let face = load_face("tests/fonts/in-house/2681c1c72d6484ed3410417f521b1b819b4e2392.ttf");
+    assert_eq!(
+        face.font_extents(Direction::BottomToTop),
+        FontExtents { ascender: 1720, descender: -328, line_gap: 0 }
+    );
+}
+
+#[test]
+fn metrics() {
+    use rustybuzz::{MetricTag, Variation};
+    use std::str::FromStr;
+
+    let mut face = load_face("tests/fonts/in-house/ab40c89624a6104e5d0a2308e448a989302f515b.ttf");
+    assert_eq!(face.metric(MetricTag::XHeight), Some(1000));
+    assert_eq!(face.metric(MetricTag::StrikeoutOffset), Some(600));
+    assert_eq!(face.metric(MetricTag::StrikeoutSize), Some(100));
+    assert_eq!(face.metric(MetricTag::UnderlineOffset), Some(-150));
+    assert_eq!(face.metric(MetricTag::HorizontalClippingDescent), Some(500));
+    // No `vhea`.
+    assert_eq!(face.metric(MetricTag::VerticalAscender), None);
+
+    // `MVAR` has `xhgt` and `stro` deltas.
+    face.set_variations(&[Variation::from_str("YTLC=600").unwrap()]);
+    assert_eq!(face.metric(MetricTag::XHeight), Some(1200));
+    assert_eq!(face.metric(MetricTag::StrikeoutOffset), Some(720));
+    assert_eq!(face.metric(MetricTag::StrikeoutSize), Some(100));
+
+    face.set_scale(1000, 1000);
+    assert_eq!(face.metric(MetricTag::XHeight), Some(600));
+}
+
+#[test]
+fn layout_info() {
+    use rustybuzz::TableIndex;
+
+    let face = load_face(SOURCE_SANS);
+    let layout = face.layout();
+    let tag = |s: &str| Tag::from_bytes_lossy(s.as_bytes());
+
+    let scripts = layout.script_tags(TableIndex::GSUB);
+    assert_eq!(scripts, [tag("DFLT"), tag("cyrl"), tag("grek"), tag("latn")]);
+    assert_eq!(layout.language_tags(TableIndex::GSUB, tag("cyrl")), [tag("BGR"), tag("MKD"), tag("SRB")]);
+    assert!(layout.language_tags(TableIndex::GSUB, tag("arab")).is_empty());
+
+    let features = layout.language_feature_tags(TableIndex::GSUB, tag("cyrl"), Some(tag("BGR")));
+    assert!(features.contains(&tag("locl")));
+    let features = layout.language_feature_tags(TableIndex::GSUB, tag("DFLT"), None);
+    assert!(!features.contains(&tag("locl")));
+    assert!(features.contains(&tag("liga")));
+    assert_eq!(layout.required_feature_tag(TableIndex::GSUB, tag("latn"), None), None);
+
+    let features = layout.feature_tags(TableIndex::GSUB);
+    assert_eq!(features.iter().filter(|t| **t == tag("liga")).count(), 1);
+
+    let names = layout.feature_names(TableIndex::GSUB, tag("ss02")).unwrap();
+    assert_eq!(names.label.as_deref(), Some("Simple a g l"));
+    let names = layout.feature_names(TableIndex::GSUB, tag("cv02")).unwrap();
+    assert_eq!(names.label.as_deref(), Some("Simple a"));
+    assert_eq!(names.tooltip, None);
+    assert_eq!(layout.feature_names(TableIndex::GSUB, tag("liga")), None);
+}
+
+#[test]
+fn glyph_alternates() {
+    use rustybuzz::{script, Language, TableIndex};
+    use std::str::FromStr;
+
+    let face = load_face(SOURCE_SANS);
+    let tag = |s: &str| Tag::from_bytes_lossy(s.as_bytes());
+    let a = face.glyph_index('a').unwrap();
+    let names = |feature: &str| -> Vec<_> {
+        face.glyph_alternates(TableIndex::GSUB, Some(script::LATIN), None, tag(feature), a).iter()
+            .map(|g| face.glyph_name(*g).unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(names("aalt"), ["a.a", "a.b", "A.s"]);
+    assert_eq!(names("salt"), ["a.a", "a.b"]);
+    assert_eq!(names("smcp"), ["A.s"]);
+    assert!(names("liga").is_empty());
+    assert!(face.glyph_alternates(TableIndex::GPOS, None, None, tag("kern"), a).is_empty());
+
+    // Only the Bulgarian language system has localized forms.
+    let de = face.glyph_index('\u{0434}').unwrap();
+    let bulgarian = Language::from_str("bg").unwrap();
+    let locl = |language| face.glyph_alternates(TableIndex::GSUB, Some(script::CYRILLIC), language, tag("locl"), de);
+    assert!(locl(None).is_empty());
+    assert_eq!(locl(Some(&bulgarian)).len(), 1);
+}
+
+#[test]
+fn would_substitute() {
+    use rustybuzz::{script, Language};
+    use std::str::FromStr;
+
+    let face = load_face(SOURCE_SANS);
+    let tag = |s: &str| Tag::from_bytes_lossy(s.as_bytes());
+    let glyphs = |text: &str| -> Vec<_> { text.chars().map(|c| face.glyph_index(c).unwrap()).collect() };
+    let latin = Some(script::LATIN);
+
+    assert!(face.would_substitute(latin, None, tag("liga"), &glyphs("ff"), false));
+    assert!(!face.would_substitute(latin, None, tag("liga"), &glyphs("ab"), false));
+    assert!(face.would_substitute(latin, None, tag("smcp"), &glyphs("a"), true));
+    assert!(!face.would_substitute(latin, None, tag("smcp"), &glyphs(" "), true));
+    assert!(!face.would_substitute(latin, None, tag("liga"), &[], false));
+
+    // `locl` is available only for some languages.
+    let cyrillic = Some(script::CYRILLIC);
+    let bulgarian = Language::from_str("bg").unwrap();
+    assert!(face.would_substitute(cyrillic, Some(&bulgarian), tag("locl"), &glyphs("д"), false));
+    assert!(!face.would_substitute(cyrillic, None, tag("locl"), &glyphs("д"), false));
+}
+
+#[test]
+fn substitute_closure() {
+    use rustybuzz::GlyphSet;
+
+    let face = load_face(SOURCE_SANS);
+    let tag = |s: &str| Tag::from_bytes_lossy(s.as_bytes());
+    let names = |set: &GlyphSet| -> Vec<_> {
+        set.iter().map(|glyph| face.glyph_name(glyph).unwrap().to_string()).collect()
+    };
+
+    let mut builder = GlyphSet::builder();
+    builder.insert(face.glyph_index('a').unwrap());
+    let glyphs = builder.finish();
+
+    let closure = face.substitute_closure(Some(&[tag("smcp")]), &glyphs);
+    assert_eq!(names(&closure), vec!["a", "A.s"]);
+
+    let closure = face.substitute_closure(Some(&[tag("salt"), tag("smcp")]), &glyphs);
+    assert_eq!(closure.len(), 4);
+    assert!(closure.contains(face.glyph_index('a').unwrap()));
+
+    let closure = face.substitute_closure(Some(&[tag("liga")]), &glyphs);
+    assert_eq!(closure.len(), 1);
+
+    // A ligature needs all of its components.
+    let mut builder = GlyphSet::builder();
+    builder.insert(face.glyph_index('f').unwrap());
+    let closure = face.substitute_closure(Some(&[tag("liga")]), &builder.finish());
+    assert_eq!(names(&closure), vec!["f", "f_f"]);
+
+    let closure = face.substitute_closure(None, &glyphs);
+    assert!(closure.len() > 4);
+}
+
+#[test]
+fn collect_lookups() {
+    use rustybuzz::TableIndex;
+
+    let face = load_face(SOURCE_SANS);
+    let tag = |s: &str| Tag::from_bytes_lossy(s.as_bytes());
+    let lookups = |table, scripts: Option<&[Tag]>, languages: Option<&[Tag]>, features: Option<&[Tag]>| -> Vec<u16> {
+        face.collect_lookups(table, scripts, languages, features).iter().collect()
+    };
+
+    let latin = [tag("latn")];
+    assert_eq!(lookups(TableIndex::GSUB, Some(&latin), None, Some(&[tag("liga")])), vec![61]);
+    assert_eq!(lookups(TableIndex::GSUB, Some(&latin), None, Some(&[tag("smcp")])), vec![9, 53, 55, 56]);
+    assert_eq!(lookups(TableIndex::GPOS, None, None, Some(&[tag("kern")])), vec![8]);
+    assert_eq!(lookups(TableIndex::GPOS, None, None, Some(&[tag("liga")])), vec![]);
+
+    // Lookup 10 is only used by contextual lookups.
+    let all = face.collect_lookups(TableIndex::GSUB, None, None, None);
+    assert_eq!(all.len(), 61);
+    assert!(!all.contains(10));
+
+    let cyrillic = [tag("cyrl")];
+    let locl = [tag("locl")];
+    assert_eq!(lookups(TableIndex::GSUB, Some(&cyrillic), Some(&[]), Some(&locl)), vec![]);
+    assert_eq!(lookups(TableIndex::GSUB, Some(&cyrillic), Some(&[tag("BGR ")]), Some(&locl)), vec![34, 37, 43, 44]);
+}
+
+#[test]
+fn collect_glyphs() {
+    use rustybuzz::TableIndex;
+    use rustybuzz::ttf_parser::GlyphId;
+
+    let face = load_face(SOURCE_SANS);
+    let f = face.glyph_index('f').unwrap();
+    let f_f = (0..face.number_of_glyphs())
+        .map(GlyphId)
+        .find(|&glyph| face.glyph_name(glyph) == Some("f_f"))
+        .unwrap();
+
+    let glyphs = face.collect_glyphs(TableIndex::GSUB, 61);
+    assert!(glyphs.before.is_empty());
+    assert!(glyphs.input.contains(f));
+    assert!(glyphs.after.is_empty());
+    assert!(glyphs.output.contains(f_f));
+
+    // A chained contextual lookup, with the output of its nested lookups.
+    let glyphs = face.collect_glyphs(TableIndex::GSUB, 13);
+    assert_eq!(glyphs.before.len(), 20);
+    assert_eq!(glyphs.input.len(), 2);
+    assert_eq!(glyphs.output.len(), 21);
+
+    let glyphs = face.collect_glyphs(TableIndex::GPOS, 8);
+    assert!(glyphs.input.contains(f));
+    assert!(glyphs.output.is_empty());
+
+    // Contextual GPOS lookups must not pull in GSUB lookups with the same index.
+    let kannada = load_face("tests/fonts/text-rendering-tests/NotoSerifKannada-Regular.ttf");
+    let glyphs = kannada.collect_glyphs(TableIndex::GPOS, 0);
+    assert!(!glyphs.input.is_empty());
+    assert!(glyphs.output.is_empty());
+    for lookup in kannada.collect_lookups(TableIndex::GPOS, None, None, None).iter() {
+        assert!(kannada.collect_glyphs(TableIndex::GPOS, lookup).output.is_empty());
+    }
+
+    let glyphs = face.collect_glyphs(TableIndex::GSUB, 1000);
+    assert!(glyphs.input.is_empty());
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn message_func() {
+    use std::sync::{Arc, Mutex};
+
+    let face = load_face(SOURCE_SANS);
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("ff");
+    buffer.set_message_func({
+        let messages = messages.clone();
+        move |view, message| {
+            let glyphs = view.serialize(SerializeFlags::NO_CLUSTERS);
+            messages.lock().unwrap().push(format!("{} {}", message, glyphs));
+            true
+        }
+    });
+
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.serialize(&face, SerializeFlags::default()), "f_f=0+578");
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.first().unwrap(), "start table GSUB f|f");
+    assert!(messages.contains(&"start lookup 61 feature 'liga' f|f".to_string()));
+    assert!(messages.contains(&"end lookup 61 feature 'liga' f_f".to_string()));
+    assert!(messages.contains(&"start table GPOS f_f+578".to_string()));
+    assert_eq!(messages.last().unwrap(), "end table GPOS f_f+578");
+
+    // Returning `false` skips the lookup.
+    let mut buffer = glyphs.clear();
+    buffer.push_str("ff");
+    buffer.set_message_func(|_, message| !message.contains("'liga'"));
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.serialize(&face, SerializeFlags::default()), "f=0+292|f=1+292");
+
+    // Sideways runs are traced as well.
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let mut buffer = glyphs.clear();
+    buffer.push_str("ff");
+    buffer.set_direction(Direction::TopToBottom);
+    buffer.set_flags(BufferFlags::ROTATE_SIDEWAYS);
+    buffer.set_message_func({
+        let messages = messages.clone();
+        move |_, message| {
+            messages.lock().unwrap().push(message.to_string());
+            true
+        }
+    });
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert!(glyphs.glyph_positions()[0].sideways());
+    assert!(messages.lock().unwrap().contains(&"start lookup 61 feature 'liga'".to_string()));
+}
+
+#[test]
+fn glyph_buffer_deserialize() {
+    use rustybuzz::GlyphBuffer;
+
+    let face = load_face(SOURCE_SANS);
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("affix Ŧ\u{301}");
+    buffer.set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+
+    let all_flags = [
+        SerializeFlags::default(),
+        SerializeFlags::NO_GLYPH_NAMES,
+        SerializeFlags::NO_CLUSTERS | SerializeFlags::NO_ADVANCES,
+        SerializeFlags::NO_POSITIONS,
+        SerializeFlags::GLYPH_FLAGS | SerializeFlags::GLYPH_EXTENTS,
+    ];
+
+    for flags in all_flags {
+        let text = glyphs.serialize(&face, flags);
+        let parsed = GlyphBuffer::deserialize(&face, &text).unwrap();
+        assert_eq!(parsed.serialize(&face, flags), text);
+    }
+
+    let parsed = GlyphBuffer::deserialize(&face, "[a=0+486|gid3=1@10,-20+0,5|4]").unwrap();
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed.glyph_infos()[0].glyph_id, face.glyph_index('a').unwrap().0 as u32);
+    assert_eq!(parsed.glyph_infos()[1].glyph_id, 3);
+    assert_eq!(parsed.glyph_infos()[1].cluster, 1);
+    assert_eq!(parsed.glyph_positions()[1].x_offset, 10);
+    assert_eq!(parsed.glyph_positions()[1].y_offset, -20);
+    assert_eq!(parsed.glyph_positions()[1].y_advance, 5);
+    assert_eq!(parsed.glyph_infos()[2].glyph_id, 4);
+
+    assert!(GlyphBuffer::deserialize(&face, "").unwrap().is_empty());
+    assert!(GlyphBuffer::deserialize(&face, "no-such-glyph=0").is_err());
+    assert!(GlyphBuffer::deserialize(&face, "a=x").is_err());
+    assert!(GlyphBuffer::deserialize(&face, "[a=0").is_err());
+}
+
+#[test]
+fn unicode_buffer_deserialize() {
+    let buffer = UnicodeBuffer::deserialize("<U+0041=0|U+0301=0|U+0042=3>").unwrap();
+    assert_eq!(buffer.len(), 3);
+
+    let face = load_face(SOURCE_SANS);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.glyph_infos().iter().map(|info| info.cluster).collect::<Vec<_>>(), [0, 3]);
+
+    // Clusters are optional.
+    let buffer = UnicodeBuffer::deserialize("U+0066|U+0066").unwrap();
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs.serialize(&face, SerializeFlags::default()), "f_f=0+578");
+
+    assert!(UnicodeBuffer::deserialize("<>").unwrap().is_empty());
+    assert!(UnicodeBuffer::deserialize("<U+D800>").is_err());
+    assert!(UnicodeBuffer::deserialize("<0041>").is_err());
+    assert!(UnicodeBuffer::deserialize("<U+0041").is_err());
+}
+
+#[test]
+fn glyph_buffer_json() {
+    use rustybuzz::{GlyphBuffer, SerializeFormat};
+
+    let face = load_face(SOURCE_SANS);
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("affix");
+    buffer.set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+
+    assert_eq!(
+        glyphs.serialize_with_format(&face, SerializeFormat::Json, SerializeFlags::default()),
+        "[{\"g\":\"a\",\"cl\":0,\"dx\":0,\"dy\":0,\"ax\":504,\"ay\":0},\
+          {\"g\":\"f_f\",\"cl\":1,\"dx\":0,\"dy\":0,\"ax\":578,\"ay\":0},\
+          {\"g\":\"i\",\"cl\":3,\"dx\":0,\"dy\":0,\"ax\":247,\"ay\":0},\
+          {\"g\":\"x\",\"cl\":4,\"dx\":0,\"dy\":0,\"ax\":447,\"ay\":0}]"
+    );
+
+    let all_flags = [
+        SerializeFlags::default(),
+        SerializeFlags::NO_GLYPH_NAMES,
+        SerializeFlags::NO_CLUSTERS | SerializeFlags::NO_ADVANCES,
+        SerializeFlags::NO_POSITIONS,
+        SerializeFlags::GLYPH_FLAGS | SerializeFlags::GLYPH_EXTENTS,
+    ];
+
+    for flags in all_flags {
+        let json = glyphs.serialize_with_format(&face, SerializeFormat::Json, flags);
+        let parsed = GlyphBuffer::deserialize_with_format(&face, &json, SerializeFormat::Json).unwrap();
+        assert_eq!(parsed.serialize_with_format(&face, SerializeFormat::Json, flags), json);
+        assert_eq!(parsed.serialize(&face, flags), glyphs.serialize(&face, flags));
+    }
+
+    let json = " [ {\"g\" : \"a\", \"cl\": 2, \"ax\": 10}, {\"g\": 3} ] ";
+    let parsed = GlyphBuffer::deserialize_with_format(&face, json, SerializeFormat::Json).unwrap();
+    assert_eq!(parsed.serialize(&face, SerializeFlags::NO_GLYPH_NAMES), "28=2+10|3=0+0");
+
+    let empty = GlyphBuffer::deserialize_with_format(&face, "[]", SerializeFormat::Json).unwrap();
+    assert_eq!(empty.serialize_with_format(&face, SerializeFormat::Json, SerializeFlags::default()), "[]");
+
+    for json in ["", "[", "[{}]", "[{\"cl\":0}]", "[{\"g\":\"no-such-glyph\"}]", "[{\"g\":\"a\"}] x"] {
+        assert!(GlyphBuffer::deserialize_with_format(&face, json, SerializeFormat::Json).is_err());
+    }
+}
+
+#[test]
+fn glyph_buffer_diff() {
+    use rustybuzz::{BufferDiffFlags, GlyphBuffer};
+
+    let face = load_face(SOURCE_SANS);
+    let parse = |text: &str| GlyphBuffer::deserialize(&face, text).unwrap();
+
+    let reference = parse("a=0+504|f_f=1+578|i=3@0,10+247#1");
+    assert_eq!(reference.diff(&reference, 0), BufferDiffFlags::empty());
+    assert!(reference.diff_glyphs(&reference, 0).is_empty());
+
+    let buffer = parse("a=0+504|f=1+292|f=2+292|i=3+247");
+    assert_eq!(buffer.diff(&reference, 0), BufferDiffFlags::LENGTH_MISMATCH);
+    assert_eq!(buffer.diff_glyphs(&reference, 0), [
+        (1, BufferDiffFlags::GLYPH_MISMATCH | BufferDiffFlags::POSITION_MISMATCH),
+        (2, BufferDiffFlags::GLYPH_MISMATCH | BufferDiffFlags::CLUSTER_MISMATCH
+            | BufferDiffFlags::GLYPH_FLAGS_MISMATCH | BufferDiffFlags::POSITION_MISMATCH),
+    ]);
+
+    let buffer = parse("a=0+505|f_f=2+578|i=3@0,10+247#1");
+    assert_eq!(buffer.diff(&reference, 0), BufferDiffFlags::CLUSTER_MISMATCH | BufferDiffFlags::POSITION_MISMATCH);
+    assert_eq!(buffer.diff(&reference, 1), BufferDiffFlags::CLUSTER_MISMATCH);
+    assert_eq!(buffer.diff_glyphs(&reference, 1), [(1, BufferDiffFlags::CLUSTER_MISMATCH)]);
+
+    let buffer = parse("a=0+504|f_f=1+578|i=3@0,10+247");
+    assert_eq!(buffer.diff(&reference, 0), BufferDiffFlags::GLYPH_FLAGS_MISMATCH);
+}
+
+#[test]
+fn unicode_buffer_serialize() {
+    use rustybuzz::Language;
+    use std::str::FromStr;
+
+    let mut buffer = UnicodeBuffer::new();
+    assert_eq!(buffer.serialize(), "<>");
+
+    buffer.push_str("سلا");
+    assert_eq!(buffer.serialize(), "<U+0633=0|U+0644=2|U+0627=4>");
+
+    buffer.guess_segment_properties();
+    buffer.set_language(Language::from_str("ar").unwrap());
+    let text = buffer.serialize();
+    assert_eq!(text, "<U+0633=0|U+0644=2|U+0627=4> direction=rtl script=Arab language=ar");
+
+    let parsed = UnicodeBuffer::deserialize(&text).unwrap();
+    assert_eq!(parsed.serialize(), text);
+    assert_eq!(parsed.direction(), Direction::RightToLeft);
+
+    let face = load_face("benches/fonts/Amiri-Regular.ttf");
+    let expected = rustybuzz::shape(&face, &[], buffer).serialize(&face, SerializeFlags::default());
+    let glyphs = rustybuzz::shape(&face, &[], parsed);
+    assert_eq!(glyphs.serialize(&face, SerializeFlags::default()), expected);
+
+    let parsed = UnicodeBuffer::deserialize("<U+0041=5> direction=ttb").unwrap();
+    assert_eq!(parsed.serialize(), "<U+0041=5> direction=ttb");
+
+    assert!(UnicodeBuffer::deserialize("<U+0041> direction=x").is_err());
+    assert!(UnicodeBuffer::deserialize("<U+0041> size=12").is_err());
+    assert!(UnicodeBuffer::deserialize("<U+0041> language").is_err());
+}
+
+#[test]
+fn replacement_invisible_and_not_found_glyphs() {
+    use rustybuzz::ttf_parser::GlyphId;
+    use rustybuzz::ClusterUnit;
+
+    let face = load_face(SOURCE_SANS);
+    let glyphs = |buffer: &rustybuzz::GlyphBuffer| -> Vec<u32> {
+        buffer.glyph_infos().iter().map(|info| info.glyph_id).collect()
+    };
+
+    let mut buffer = UnicodeBuffer::new();
+    assert_eq!(buffer.replacement_codepoint(), '\u{FFFD}');
+    assert_eq!(buffer.invisible_glyph(), None);
+    assert_eq!(buffer.not_found_glyph(), GlyphId(0));
+
+    buffer.push_str("x\u{200D}\u{0E01}");
+    let default = rustybuzz::shape(&face, &[], buffer);
+    let space = face.glyph_index(' ').unwrap();
+    let x = face.glyph_index('x').unwrap();
+    assert_eq!(glyphs(&default), [u32::from(x.0), u32::from(space.0), 0]);
+
+    let mut buffer = default.clear();
+    buffer.set_invisible_glyph(Some(GlyphId(3)));
+    buffer.set_not_found_glyph(GlyphId(5));
+    buffer.push_str("x\u{200D}\u{0E01}");
+    let glyphs_buffer = rustybuzz::shape(&face, &[], buffer);
+    assert_eq!(glyphs(&glyphs_buffer), [u32::from(x.0), 3, 5]);
+
+    // Settings survive clearing.
+    let mut buffer = glyphs_buffer.clear();
+    assert_eq!(buffer.invisible_glyph(), Some(GlyphId(3)));
+    assert_eq!(buffer.not_found_glyph(), GlyphId(5));
+
+    buffer.set_replacement_codepoint('x');
+    buffer.push_utf8(b"a\xFFa", 0..3, ClusterUnit::Utf8);
+    assert_eq!(buffer.serialize(), "<U+0061=0|U+0078=1|U+0061=2>");
+}
+
+#[test]
+fn vertical_orientation() {
+    use rustybuzz::{vertical_orientation, VerticalOrientation};
+
+    assert_eq!(vertical_orientation('a'), VerticalOrientation::Rotated);
+    assert_eq!(vertical_orientation('§'), VerticalOrientation::Upright);
+    assert_eq!(vertical_orientation('日'), VerticalOrientation::Upright);
+    assert_eq!(vertical_orientation('ッ'), VerticalOrientation::TransformedUpright);
+    assert_eq!(vertical_orientation('「'), VerticalOrientation::TransformedRotated);
+    assert_eq!(vertical_orientation('ー'), VerticalOrientation::TransformedRotated);
+    assert_eq!(vertical_orientation('\u{20000}'), VerticalOrientation::Upright);
+    assert!(VerticalOrientation::TransformedUpright.is_upright());
+    assert!(!VerticalOrientation::TransformedRotated.is_upright());
+}
+
+#[test]
+fn rotate_sideways() {
+    use rustybuzz::BufferFlags;
+
+    let face = load_face(SOURCE_SANS);
+    let extents = face.font_extents(Direction::LeftToRight);
+    let center = (extents.ascender + extents.descender) / 2;
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("a\u{301}§x");
+    buffer.set_direction(Direction::TopToBottom);
+    let upright = rustybuzz::shape(&face, &[], buffer);
+    assert!(upright.glyph_positions().iter().all(|pos| !pos.sideways()));
+    let upright_section = (upright.glyph_infos()[1], upright.glyph_positions()[1]);
+
+    let mut buffer = upright.clear();
+    buffer.push_str("a\u{301}§x");
+    buffer.set_direction(Direction::TopToBottom);
+    buffer.set_flags(BufferFlags::ROTATE_SIDEWAYS);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let infos = glyphs.glyph_infos().to_vec();
+    let positions = glyphs.glyph_positions().to_vec();
+
+    let expected = glyphs.serialize(&face, SerializeFlags::default());
+    let sideways: Vec<_> = positions.iter().map(|pos| pos.sideways()).collect();
+    assert_eq!(sideways, [true, false, true]);
+    let clusters: Vec<_> = infos.iter().map(|info| info.cluster).collect();
+    assert_eq!(clusters, [0, 3, 5]);
+
+    let mut buffer = glyphs.clear();
+    buffer.push_str("a\u{301}");
+    let horizontal = rustybuzz::shape(&face, &[], buffer);
+    let aacute = horizontal.glyph_positions()[0];
+
+    assert_eq!(infos[0].glyph_id, horizontal.glyph_infos()[0].glyph_id);
+    assert_eq!(positions[0].x_advance, 0);
+    assert_eq!(positions[0].y_advance, -aacute.x_advance);
+    assert_eq!(positions[0].x_offset, -center);
+    assert_eq!(positions[2].y_advance, -447);
+
+    assert_eq!(infos[1].glyph_id, upright_section.0.glyph_id);
+    assert_eq!(positions[1].y_advance, upright_section.1.y_advance);
+    assert_eq!(positions[1].x_offset, upright_section.1.x_offset);
+    assert_eq!(positions[1].y_offset, upright_section.1.y_offset);
+
+    // A cached plan per run.
+    let mut buffer = horizontal.clear();
+    buffer.push_str("a\u{301}§x");
+    buffer.set_direction(Direction::TopToBottom);
+    buffer.set_flags(BufferFlags::ROTATE_SIDEWAYS);
+    let mut cache = PlanCache::new(2);
+    let cached = cache.shape(&face, &[], buffer);
+    assert_eq!(cached.serialize(&face, SerializeFlags::default()), expected);
+    assert!(cached.glyph_positions()[0].sideways());
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+    // A single plan can't shape both runs.
+    let mut buffer = cached.clear();
+    buffer.push_str("a\u{301}§x");
+    buffer.set_direction(Direction::TopToBottom);
+    buffer.set_flags(BufferFlags::ROTATE_SIDEWAYS);
+    let plan = ShapePlan::new(&face, Direction::TopToBottom, None, None, &[]);
+    let result = rustybuzz::shape_with_plan(&plan, &face, &[], buffer);
+    assert_eq!(result.err().map(|e| e.0), Some(ShapePlanError::SidewaysRuns));
+}
+
+#[test]
+fn vertical_origin_without_vmtx() {
+    let face = load_face(SOURCE_SANS);
+    let glyph = face.glyph_index('§').unwrap();
+    let extents = face.glyph_extents(glyph).unwrap();
+
+    // No `vmtx`, so glyphs are placed by their extents and advance by an em.
+    assert_eq!(face.glyph_v_advance(glyph), -face.units_per_em());
+    assert_eq!(face.glyph_v_origin(glyph), (face.glyph_h_advance(glyph) / 2, extents.y_bearing));
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("§");
+    buffer.set_direction(Direction::TopToBottom);
+    let glyphs = rustybuzz::shape(&face, &[], buffer);
+    let pos = glyphs.glyph_positions()[0];
+    assert_eq!(pos.y_advance, -face.units_per_em());
+    assert_eq!((pos.x_offset, pos.y_offset), (-face.glyph_h_advance(glyph) / 2, -extents.y_bearing));
+}
@@ -7,7 +7,7 @@ struct Args {
     direction: Option<rustybuzz::Direction>,
     language: Option<rustybuzz::Language>,
     script: Option<rustybuzz::Script>,
-    #[allow(dead_code)] remove_default_ignorables: bool, // we don't use it, but have to parse it anyway
+    remove_default_ignorables: bool,
     cluster_level: rustybuzz::BufferClusterLevel,
     features: Vec<String>,
     no_glyph_names: bool,
@@ -52,6 +52,7 @@ fn parse_cluster(s: &str) -> Result<rustybuzz::BufferClusterLevel, String> {
         "0" => Ok(rustybuzz::BufferClusterLevel::MonotoneGraphemes),
         "1" => Ok(rustybuzz::BufferClusterLevel::MonotoneCharacters),
         "2" => Ok(rustybuzz::BufferClusterLevel::Characters),
+        "3" => Ok(rustybuzz::BufferClusterLevel::Graphemes),
         _ => Err(format!("invalid cluster level"))
     }
 }
@@ -86,6 +87,10 @@ pub fn shape(font_path: &str, text: &str, options: &str) -> String {
         buffer.set_script(script);
     }
 
+    if args.remove_default_ignorables {
+        buffer.set_flags(rustybuzz::BufferFlags::REMOVE_DEFAULT_IGNORABLES);
+    }
+
     buffer.set_cluster_level(args.cluster_level);
     buffer.reset_clusters();
 